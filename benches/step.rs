@@ -0,0 +1,52 @@
+//! Compare stepping the ring buffer of `TScale` with shifting the whole array.
+//!
+//! Run with `cargo bench --bench step`.
+
+use std::{hint::black_box, time::Instant};
+
+use tscale_sequence::tscale_sequence::TScale;
+
+const STEPS: usize = 10000;
+
+/// The previous implementation: clone every value one slot down on each step
+fn shift_step<const C: usize>(array: &mut [f64; C], weight: &[f64; C]) -> f64 {
+    let last_value = array
+        .iter()
+        .zip(weight.iter().rev())
+        .fold(0.0, |acc, (a, b)| acc + a * b);
+    let first_value = array[0];
+    (0..C - 1).for_each(|index| array[index] = array[index + 1]);
+    array[C - 1] = last_value;
+    first_value
+}
+
+fn bench<const C: usize>() {
+    // β sums to 1, so the values stay finite
+    let weight = [1.0 / C as f64; C];
+
+    let mut array = [1.0; C];
+    let start = Instant::now();
+    for _ in 0..STEPS {
+        black_box(shift_step(&mut array, &weight));
+    }
+    let shift = start.elapsed();
+
+    let mut tscale = TScale::new_with_config([1.0; C], weight);
+    let start = Instant::now();
+    tscale.iter().take(STEPS).for_each(|value| {
+        black_box(value);
+    });
+    let ring = start.elapsed();
+
+    println!(
+        "C = {C:>3}: shift {:>10.1?}, ring buffer {:>10.1?}, {:.2}x",
+        shift,
+        ring,
+        shift.as_secs_f64() / ring.as_secs_f64()
+    );
+}
+
+fn main() {
+    bench::<64>();
+    bench::<512>();
+}
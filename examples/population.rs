@@ -9,7 +9,9 @@
 //!
 //! The goal is to obtain the newborn sequence and roughly evaluate the coefficient relationship between the number of newborns and the previous year's newborns.
 
-use tscale_sequence::{tscale_rate::compute_limit_rate, tscale_sequence:: TScale};
+use tscale_sequence::{
+    tscale_leslie::Leslie, tscale_rate::compute_limit_rate, tscale_sequence::TScale,
+};
 
 fn main() {
     let start_people = [1.0, 1.0, 1.0, 1.0, 1.0];
@@ -28,4 +30,28 @@ fn main() {
     // if you want to know after 100 round population
     println!("the 100 round population is {}",rate.powf(50.0)*population);
 
+    // or solve the closed form and evaluate any round directly
+    let closed_form = TScale::new_with_config(start_people, weight).closed_form();
+    println!(
+        "population ≈ {} * {}^t",
+        closed_form.dominant_coefficient(),
+        closed_form.dominant_root()
+    );
+    // term 99 is the 50 round population above, so the 150 round population is term 199
+    println!("the 150 round population is {}", closed_form.evaluate(199));
+
+    // the sequence above assumes nobody dies before 100, add survival rates per age group
+    let survival = [0.95, 0.9, 0.8, 0.5, 0.0];
+    let mut leslie = Leslie::new(start_people, weight, survival);
+    for _ in 0..100 {
+        leslie.step();
+    }
+    println!(
+        "with deaths: growth {}, total {}, newborns {}",
+        leslie.dominant_eigenvalue().unwrap(),
+        leslie.total(),
+        leslie.births()
+    );
+    println!("stable age distribution {:?}", leslie.stable_age_distribution().unwrap());
+
 }
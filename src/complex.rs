@@ -0,0 +1,107 @@
+//! A minimal complex number, used for the roots of the characteristic polynomial.
+
+use std::{
+    fmt::{self, Display},
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub const ZERO: Self = Self::new(0.0, 0.0);
+    pub const ONE: Self = Self::new(1.0, 0.0);
+
+    pub const fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    /// create from modulus and argument
+    pub fn from_polar(norm: f64, arg: f64) -> Self {
+        let (sin, cos) = arg.sin_cos();
+        Self::new(norm * cos, norm * sin)
+    }
+
+    /// modulus |z|
+    pub fn norm(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    /// argument in (-π, π]
+    pub fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    /// z^n
+    pub fn powu(self, n: usize) -> Self {
+        if n == 0 {
+            return Self::ONE;
+        }
+        Self::from_polar(self.norm().powf(n as f64), self.arg() * n as f64)
+    }
+}
+
+impl From<f64> for Complex {
+    fn from(re: f64) -> Self {
+        Self::new(re, 0.0)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.re.mul_add(rhs.re, -self.im * rhs.im),
+            self.re.mul_add(rhs.im, self.im * rhs.re),
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        let denominator = rhs.re.mul_add(rhs.re, rhs.im * rhs.im);
+        Self::new(
+            self.re.mul_add(rhs.re, self.im * rhs.im) / denominator,
+            self.im.mul_add(rhs.re, -self.re * rhs.im) / denominator,
+        )
+    }
+}
+
+impl Neg for Complex {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::new(-self.re, -self.im)
+    }
+}
+
+impl Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.im < 0.0 {
+            write!(f, "{}-{}i", self.re, -self.im)
+        } else {
+            write!(f, "{}+{}i", self.re, self.im)
+        }
+    }
+}
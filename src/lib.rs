@@ -36,4 +36,15 @@
 #![warn(clippy::all, clippy::nursery)]
 
 pub mod tscale_sequence;
-pub mod tscale_rate;
\ No newline at end of file
+pub mod tscale_rate;
+pub mod tscale_jump;
+pub mod complex;
+pub mod tscale_closed_form;
+pub mod tscale_solver;
+pub mod tscale_fit;
+pub mod tscale_dyn;
+pub mod tscale_forcing;
+pub mod tscale_schedule;
+pub mod tscale_stochastic;
+pub mod tscale_leslie;
+pub mod tscale_demographics;
@@ -0,0 +1,210 @@
+//! Closed-form (Binet-style) expression of a t-scale sequence.
+//!
+//! Every t-scale sequence can be written as
+//!
+//! at = Σ cj * t^k * rj^t
+//!
+//! where rj are the [characteristic roots](crate::tscale_rate::characteristic_roots)
+//! and k runs from 0 to the multiplicity of rj minus 1. Once the coefficients cj are
+//! solved from the initial values, any term can be evaluated in O(C).
+
+use crate::{complex::Complex, tscale_rate::characteristic_roots};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Term {
+    root: Complex,
+    power: usize,
+    coefficient: Complex,
+}
+
+impl Term {
+    /// t^k * r^t, a root at 0 contributes only to the first terms
+    fn basis(root: Complex, power: usize, t: usize) -> Complex {
+        if root == Complex::ZERO {
+            if t == power {
+                Complex::ONE
+            } else {
+                Complex::ZERO
+            }
+        } else {
+            Complex::from((t as f64).powf(power as f64)) * root.powu(t)
+        }
+    }
+
+    fn evaluate(&self, t: usize) -> Complex {
+        self.coefficient * Self::basis(self.root, self.power, t)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClosedForm {
+    terms: Vec<Term>,
+}
+
+impl ClosedForm {
+    /// solve the coefficients for the given initial values and weights
+    ///
+    /// `array` and `weight` use the same order as
+    /// [`TScale::new_with_config`](crate::tscale_sequence::TScale::new_with_config),
+    /// `array[0]` is term 0.
+    pub fn new(array: &[f64], weight: &[f64]) -> Self {
+        if array.len() != weight.len() {
+            panic!("array and weight must have the same length");
+        }
+        let mut terms = characteristic_roots(weight)
+            .into_iter()
+            .flat_map(|root| {
+                (0..root.multiplicity).map(move |power| Term {
+                    root: root.value,
+                    power,
+                    coefficient: Complex::ZERO,
+                })
+            })
+            .collect::<Vec<_>>();
+        let matrix = (0..array.len())
+            .map(|t| {
+                terms
+                    .iter()
+                    .map(|term| Term::basis(term.root, term.power, t))
+                    .collect()
+            })
+            .collect();
+        let rhs = array.iter().map(|a| Complex::from(*a)).collect();
+        terms
+            .iter_mut()
+            .zip(solve(matrix, rhs))
+            .for_each(|(term, coefficient)| term.coefficient = coefficient);
+        Self { terms }
+    }
+
+    /// evaluate the t-th term, where term 0 is `array[0]`
+    pub fn evaluate(&self, t: usize) -> f64 {
+        self.terms
+            .iter()
+            .fold(Complex::ZERO, |sum, term| sum + term.evaluate(t))
+            .re
+    }
+
+    /// the root with the largest modulus
+    ///
+    /// For valid betas it is real, simple and equal to
+    /// [`compute_limit_rate`](crate::tscale_rate::compute_limit_rate).
+    pub fn dominant_root(&self) -> f64 {
+        self.terms[0].root.re
+    }
+
+    /// the coefficient c of the dominant root, so at ≈ c * r^t for large t
+    pub fn dominant_coefficient(&self) -> f64 {
+        self.terms[0].coefficient.re
+    }
+
+    /// the limits of at/a{t-1} for each phase t mod d, where d is the
+    /// [period](crate::tscale_rate::period) of the weights
+    ///
+    /// For primitive weights there is a single phase and this is the limit rate.
+    /// Otherwise the d roots with the dominant modulus keep the ratio oscillating.
+    /// A phase whose subsequence is zero yields a non-finite ratio.
+    pub fn phase_ratios(&self) -> Vec<f64> {
+        let modulus = self.terms[0].root.norm();
+        let dominant = self
+            .terms
+            .iter()
+            .filter(|term| term.root.norm() >= modulus * (1.0 - 1e-6))
+            .collect::<Vec<_>>();
+        let phases = dominant.iter().filter(|term| term.power == 0).count();
+        let evaluate = |t: usize| {
+            dominant
+                .iter()
+                .fold(Complex::ZERO, |sum, term| sum + term.evaluate(t))
+                .re
+        };
+        // the dominant part repeats every `phases` terms up to a factor r^phases
+        (phases..2 * phases)
+            .map(|t| evaluate(t) / evaluate(t - 1))
+            .collect()
+    }
+}
+
+/// Solve a linear system with Gaussian elimination and partial pivoting
+fn solve(mut matrix: Vec<Vec<Complex>>, mut rhs: Vec<Complex>) -> Vec<Complex> {
+    let n = rhs.len();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|a, b| {
+                matrix[*a][col]
+                    .norm()
+                    .total_cmp(&matrix[*b][col].norm())
+            })
+            .unwrap_or(col);
+        matrix.swap(col, pivot);
+        rhs.swap(col, pivot);
+        let (upper, lower) = matrix.split_at_mut(col + 1);
+        let pivot_row = &upper[col];
+        for (row, values) in lower.iter_mut().enumerate() {
+            let factor = values[col] / pivot_row[col];
+            values
+                .iter_mut()
+                .zip(pivot_row.iter())
+                .skip(col)
+                .for_each(|(value, pivot)| *value = *value - factor * *pivot);
+            rhs[col + 1 + row] = rhs[col + 1 + row] - factor * rhs[col];
+        }
+    }
+    let mut solution = vec![Complex::ZERO; n];
+    for row in (0..n).rev() {
+        let sum = (row + 1..n).fold(rhs[row], |sum, k| sum - matrix[row][k] * solution[k]);
+        solution[row] = sum / matrix[row][row];
+    }
+    solution
+}
+
+#[cfg(test)]
+mod tests {
+    use approximately::ApproxEq;
+
+    use super::*;
+    use crate::tscale_jump::nth_term;
+
+    #[test]
+    fn test_closed_form() {
+        // Fibonacci sequence, at = (φ^t - ψ^t) / √5
+        let closed_form = ClosedForm::new(&[0.0, 1.0], &[1.0, 1.0]);
+        closed_form.dominant_root().assert_approx(1.618034);
+        closed_form
+            .dominant_coefficient()
+            .assert_approx(1.0 / 5.0_f64.sqrt());
+        closed_form.evaluate(40).assert_approx(102_334_155.0);
+
+        // repeated root: at = 1 + 2t
+        let closed_form = ClosedForm::new(&[1.0, 3.0], &[2.0, -1.0]);
+        closed_form.evaluate(10).assert_approx(21.0);
+
+        // root at 0
+        let array = [1.0, 1.0, 1.0, 1.0, 1.0];
+        let weight = [0.4, 1.2, 0.3, 0.1, 0.0];
+        let closed_form = ClosedForm::new(&array, &weight);
+        for t in 0..30 {
+            closed_form
+                .evaluate(t)
+                .assert_approx(nth_term(&array, &weight, t));
+        }
+    }
+
+    #[test]
+    fn test_phase_ratios() {
+        let phase_ratios = ClosedForm::new(&[1.0, 2.0], &[0.0, 1.0]).phase_ratios();
+        assert_eq!(phase_ratios.len(), 2);
+        phase_ratios[0].assert_approx(0.5);
+        phase_ratios[1].assert_approx(2.0);
+
+        let phase_ratios = ClosedForm::new(&[1.0, 2.0, 3.0], &[0.0, 0.0, 2.0]).phase_ratios();
+        assert_eq!(phase_ratios.len(), 3);
+        phase_ratios[0].assert_approx(2.0 / 3.0);
+        phase_ratios[1].assert_approx(2.0);
+        phase_ratios[2].assert_approx(1.5);
+
+        let phase_ratios = ClosedForm::new(&[0.0, 1.0], &[1.0, 1.0]).phase_ratios();
+        assert_eq!(phase_ratios.len(), 1);
+        phase_ratios[0].assert_approx(1.618034);
+    }
+}
@@ -0,0 +1,122 @@
+//! Demographic summary of a weight vector.
+//!
+//! Reads the betas as fecundities of age groups, like the README example, and reports
+//! the usual population metrics in one place:
+//!
+//! ```rust
+//! # use tscale_sequence::tscale_demographics::{Demographics, GrowthClass};
+//! let demographics = Demographics::new(&[0.4, 1.2, 0.3, 0.1, 0.0]).unwrap();
+//! assert_eq!(demographics.growth_class, GrowthClass::Growing);
+//! println!("doubles every {:?} ticks", demographics.doubling_time);
+//! ```
+
+use std::f64::consts::LN_2;
+
+use approximately::ApproxEq;
+
+use crate::tscale_rate::{try_compute_limit_rate, RateError};
+
+/// The three regimes of the README: Σ βi < 1, Σ βi = 1 and Σ βi > 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrowthClass {
+    /// Σ βi < 1, the limit rate is below 1 and the sequence dies out
+    Declining,
+    /// Σ βi = 1, the limit rate is 1
+    Stationary,
+    /// Σ βi > 1, the limit rate is above 1
+    Growing,
+}
+
+/// Demographic metrics of the betas, see [`Demographics::new`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Demographics {
+    /// R0 = Σ βi, the offspring of one individual over its life
+    pub net_reproductive_number: f64,
+    /// the limit rate r from [`compute_limit_rate`](crate::tscale_rate::compute_limit_rate)
+    pub limit_rate: f64,
+    /// ln r, the continuous growth rate per tick
+    pub intrinsic_rate: f64,
+    /// T = Σ i*βi*r^-i / Σ βi*r^-i, the mean age of the parents of the newborns
+    pub generation_time: f64,
+    /// ln 2 / ln r, the ticks until the sequence doubles, if it is growing
+    pub doubling_time: Option<f64>,
+    /// -ln 2 / ln r, the ticks until the sequence halves, if it is declining
+    pub half_life: Option<f64>,
+    pub growth_class: GrowthClass,
+}
+
+impl Demographics {
+    /// compute the metrics of the betas, in the order of
+    /// [`TScale::new_with_config`](crate::tscale_sequence::TScale::new_with_config)
+    pub fn new(beta: &[f64]) -> Result<Self, RateError> {
+        let limit_rate = try_compute_limit_rate(beta)?;
+        let net_reproductive_number = beta.iter().sum::<f64>();
+        let growth_class = if net_reproductive_number.approx(1.0) {
+            GrowthClass::Stationary
+        } else if net_reproductive_number < 1.0 {
+            GrowthClass::Declining
+        } else {
+            GrowthClass::Growing
+        };
+        let (weighted_age, weight) = beta.iter().enumerate().fold(
+            (0.0, 0.0),
+            |(weighted_age, weight), (i, beta)| {
+                let discounted = beta * limit_rate.powi(-(i as i32) - 1);
+                (discounted.mul_add((i + 1) as f64, weighted_age), weight + discounted)
+            },
+        );
+        let intrinsic_rate = limit_rate.ln();
+        Ok(Self {
+            net_reproductive_number,
+            limit_rate,
+            intrinsic_rate,
+            generation_time: weighted_age / weight,
+            doubling_time: (growth_class == GrowthClass::Growing).then(|| LN_2 / intrinsic_rate),
+            half_life: (growth_class == GrowthClass::Declining).then(|| -LN_2 / intrinsic_rate),
+            growth_class,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tscale_sequence::TScale;
+
+    #[test]
+    fn test_demographics() {
+        // Fibonacci sequence
+        let demographics = Demographics::new(&[1.0, 1.0]).unwrap();
+        assert_eq!(demographics.growth_class, GrowthClass::Growing);
+        demographics.net_reproductive_number.assert_approx(2.0);
+        demographics.limit_rate.assert_approx(1.618034);
+        demographics.intrinsic_rate.assert_approx(1.618034_f64.ln());
+        // φ^-1 + 2φ^-2
+        demographics.generation_time.assert_approx(1.381966);
+        demographics.doubling_time.unwrap().assert_approx(1.440420);
+        assert_eq!(demographics.half_life, None);
+
+        let demographics = Demographics::new(&[0.5, 0.5]).unwrap();
+        assert_eq!(demographics.growth_class, GrowthClass::Stationary);
+        demographics.generation_time.assert_approx(1.5);
+        assert_eq!((demographics.doubling_time, demographics.half_life), (None, None));
+
+        assert_eq!(Demographics::new(&[]), Err(RateError::Empty));
+    }
+
+    #[test]
+    fn test_half_life() {
+        let weight = [0.3, 0.2, 0.1];
+        let demographics = Demographics::new(&weight).unwrap();
+        assert_eq!(demographics.growth_class, GrowthClass::Declining);
+        let half_life = demographics.half_life.unwrap();
+
+        let mut tscale = TScale::new_with_config([1.0, 1.0, 1.0], weight);
+        tscale.iter().nth(100);
+        let start = tscale.step();
+        let ticks = 20;
+        tscale.iter().nth(ticks - 2);
+        let halvings = (start / tscale.step()).log2();
+        (ticks as f64 / halvings).assert_approx(half_life);
+    }
+}
@@ -0,0 +1,351 @@
+//! A t-scale sequence whose order is only known at runtime.
+//!
+//! [`TScale`] needs the order C at compile time. [`DynTScale`] stores the initial values
+//! and weights in boxed slices instead, e.g. when the number of age groups comes from a
+//! config file, and converts to and from [`TScale`] when the length matches.
+
+use std::{
+    error::Error,
+    fmt::{self, Debug, Display},
+    ops::{Add, Div, Mul},
+};
+
+use crate::{
+    tscale_jump::{self, JumpStrategy},
+    tscale_sequence::{Bounded, RingBuffer, TScale},
+};
+
+/// Why a [`DynTScale`] could not be created or converted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DynTScaleError {
+    /// the order must be greater than 0
+    Empty,
+    /// `array` and `weight` have different lengths
+    LengthMismatch { array: usize, weight: usize },
+    /// the order does not match the const generic order of [`TScale`]
+    OrderMismatch { expected: usize, found: usize },
+}
+
+impl Display for DynTScaleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "C must be greater than 0"),
+            Self::LengthMismatch { array, weight } => write!(
+                f,
+                "array has {array} values but weight has {weight} values"
+            ),
+            Self::OrderMismatch { expected, found } => {
+                write!(f, "expected order {expected}, found {found}")
+            }
+        }
+    }
+}
+
+impl Error for DynTScaleError {}
+
+#[derive(Clone)]
+pub struct DynTScale<T> {
+    array: Box<[T]>,
+    weight: Box<[T]>,
+    /// `array` is a ring buffer, the oldest value is at `head`
+    head: usize,
+}
+
+impl<T> DynTScale<T> {
+    /// create new recursive from slices, Vecs or boxed slices of equal length
+    ///
+    /// Same order as [`TScale::new_with_config`]: `array` is ascending and `weight`
+    /// starts with the most recent coefficient.
+    pub fn new(
+        array: impl Into<Box<[T]>>,
+        weight: impl Into<Box<[T]>>,
+    ) -> Result<Self, DynTScaleError> {
+        let (array, weight) = (array.into(), weight.into());
+        if array.len() != weight.len() {
+            return Err(DynTScaleError::LengthMismatch {
+                array: array.len(),
+                weight: weight.len(),
+            });
+        }
+        if array.is_empty() {
+            return Err(DynTScaleError::Empty);
+        }
+        Ok(Self {
+            array,
+            weight,
+            head: 0,
+        })
+    }
+
+    /// the order C, i.e. the number of weights
+    pub fn order(&self) -> usize {
+        self.weight.len()
+    }
+
+    /// the values of the ring buffer, oldest first
+    fn logical(&self) -> impl Iterator<Item = &T> {
+        let (newer, older) = self.array.split_at(self.head);
+        older.iter().chain(newer)
+    }
+
+    /// into iterator
+    ///
+    /// It generates 10000 values by default, use [`Bounded::with_len`] or
+    /// [`Bounded::unbounded`] to change that.
+    pub fn iter(&mut self) -> Bounded<DynTScaleIter<'_, T>> {
+        Bounded::new(DynTScaleIter {
+            array: &mut self.array,
+            weight: &self.weight,
+            head: &mut self.head,
+        })
+    }
+}
+
+impl<T> DynTScale<T>
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    /// compute the n-th term without stepping through the sequence
+    pub fn nth_term(&self, n: usize) -> T {
+        tscale_jump::nth_term(&self.window(), &self.weight, n)
+    }
+
+    /// compute the n-th term with the given jump strategy
+    pub fn nth_term_with(&self, n: usize, strategy: JumpStrategy) -> T {
+        tscale_jump::nth_term_with(&self.window(), &self.weight, n, strategy)
+    }
+
+    /// the values of the ring buffer, oldest first
+    fn window(&self) -> Vec<T> {
+        self.logical().cloned().collect()
+    }
+}
+
+/// equal if the last C values and the weights are, wherever the ring buffer starts
+impl<T: PartialEq> PartialEq for DynTScale<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.weight == other.weight && self.logical().eq(other.logical())
+    }
+}
+
+impl<T: Eq> Eq for DynTScale<T> {}
+
+impl<T: Debug> Debug for DynTScale<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynTScale")
+            .field("array", &DebugWindow(self))
+            .field("weight", &self.weight)
+            .finish()
+    }
+}
+
+/// prints the ring buffer of a [`DynTScale`] oldest first
+struct DebugWindow<'a, T>(&'a DynTScale<T>);
+
+impl<T: Debug> Debug for DebugWindow<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.0.logical()).finish()
+    }
+}
+
+impl<T, const C: usize> From<TScale<T, C>> for DynTScale<T> {
+    fn from(tscale: TScale<T, C>) -> Self {
+        let TScale {
+            mut array,
+            weight,
+            head,
+        } = tscale;
+        array.rotate_left(head);
+        Self {
+            array: Box::new(array),
+            weight: Box::new(weight),
+            head: 0,
+        }
+    }
+}
+
+impl<T, const C: usize> TryFrom<DynTScale<T>> for TScale<T, C> {
+    type Error = DynTScaleError;
+
+    fn try_from(tscale: DynTScale<T>) -> Result<Self, Self::Error> {
+        let mismatch = DynTScaleError::OrderMismatch {
+            expected: C,
+            found: tscale.order(),
+        };
+        let DynTScale {
+            mut array,
+            weight,
+            head,
+        } = tscale;
+        array.rotate_left(head);
+        let array: Box<[T; C]> = array.try_into().map_err(|_| mismatch)?;
+        let weight: Box<[T; C]> = weight.try_into().map_err(|_| mismatch)?;
+        Ok(Self {
+            array: *array,
+            weight: *weight,
+            head: 0,
+        })
+    }
+}
+
+/// Recursive iterator over a borrowed [`DynTScale`], see [`DynTScale::iter`]
+pub struct DynTScaleIter<'a, T> {
+    array: &'a mut [T],
+    weight: &'a [T],
+    head: &'a mut usize,
+}
+
+impl<'a, T> RingBuffer for DynTScaleIter<'a, T>
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    type Value = T;
+
+    fn step(&mut self) -> T {
+        tscale_jump::step_ring(self.array, self.weight, self.head)
+    }
+
+    fn skip(&mut self, n: usize) {
+        tscale_jump::skip_ring(self.array, self.weight, self.head, n);
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut DynTScale<T>
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    type Item = T;
+    type IntoIter = Bounded<DynTScaleIter<'a, T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Recursive iterator over an owned [`DynTScale`]
+pub struct DynTScaleIntoIter<T> {
+    array: Box<[T]>,
+    weight: Box<[T]>,
+    head: usize,
+}
+
+impl<T> RingBuffer for DynTScaleIntoIter<T>
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    type Value = T;
+
+    fn step(&mut self) -> T {
+        tscale_jump::step_ring(&mut self.array, &self.weight, &mut self.head)
+    }
+
+    fn skip(&mut self, n: usize) {
+        tscale_jump::skip_ring(&mut self.array, &self.weight, &mut self.head, n);
+    }
+}
+
+impl<T> IntoIterator for DynTScale<T>
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    type Item = T;
+    type IntoIter = Bounded<DynTScaleIntoIter<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Bounded::new(DynTScaleIntoIter {
+            array: self.array,
+            weight: self.weight,
+            head: self.head,
+        })
+    }
+}
+
+/// compute rate with an+1 and an, like
+/// [`compute_rate_with_data`](crate::tscale_sequence::compute_rate_with_data)
+pub fn compute_rate_with_dyn_data<T>(
+    count: usize,
+    array: impl Into<Box<[T]>>,
+    weight: impl Into<Box<[T]>>,
+) -> Result<impl Iterator<Item = T>, DynTScaleError>
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default + Div<Output = T>,
+{
+    let take_list = DynTScale::new(array, weight)?.into_iter().take(count);
+    Ok(take_list.scan(None, |v, next: T| {
+        v.replace(next.clone())
+            .map_or_else(|| Some(T::default()), |v| Some(next / v))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use approximately::ApproxEq;
+
+    use super::*;
+    use crate::tscale_sequence::compute_rate_with_data;
+
+    #[test]
+    fn test_dyn_sequence() {
+        let array = vec![1.0, 1.0, 1.0];
+        let weight = vec![0.4, 1.2, 0.3];
+        let mut tscale = DynTScale::new(array.clone(), weight.clone()).unwrap();
+        let mut expected = TScale::new_with_config([1.0, 1.0, 1.0], [0.4, 1.2, 0.3]);
+        tscale
+            .iter()
+            .zip(expected.iter())
+            .take(100)
+            .for_each(|(value, expected)| value.assert_approx(expected));
+
+        compute_rate_with_dyn_data(50, array, weight)
+            .unwrap()
+            .last()
+            .unwrap()
+            .assert_approx(
+                compute_rate_with_data(50, [1.0, 1.0, 1.0], [0.4, 1.2, 0.3])
+                    .last()
+                    .unwrap(),
+            );
+    }
+
+    #[test]
+    fn test_conversion() {
+        assert_eq!(
+            DynTScale::new(vec![1.0, 2.0], vec![1.0]),
+            Err(DynTScaleError::LengthMismatch { array: 2, weight: 1 })
+        );
+        assert_eq!(
+            DynTScale::<f64>::new(vec![], vec![]),
+            Err(DynTScaleError::Empty)
+        );
+
+        let mut tscale = TScale::new_with_config([0.0, 1.0, 1.0], [1.0, 1.0, 0.0]);
+        tscale.iter().take(2).for_each(drop);
+        DynTScale::from(tscale).nth_term(8).assert_approx(55.0);
+
+        let tscale = DynTScale::from(TScale::new_with_config([0.0, 1.0], [1.0, 1.0]));
+        assert_eq!(tscale.order(), 2);
+        tscale.nth_term(10).assert_approx(55.0);
+        assert_eq!(
+            TScale::<f64, 3>::try_from(tscale.clone()).err(),
+            Some(DynTScaleError::OrderMismatch { expected: 3, found: 2 })
+        );
+        let mut unbounded = tscale.clone().into_iter().unbounded();
+        assert_eq!(unbounded.by_ref().take(20_000).count(), 20_000);
+        assert_eq!(tscale.clone().into_iter().with_len(3).len(), 3);
+        let tscale: TScale<f64, 2> = tscale.try_into().unwrap();
+        tscale.into_iter().nth(10).unwrap().assert_approx(55.0);
+    }
+
+    #[test]
+    fn test_logical_equality() {
+        let mut stepped = DynTScale::new(vec![1, 1], vec![1, 1]).unwrap();
+        stepped.iter().take(3).for_each(drop);
+        // the window is [3, 5], but the ring buffer holds [5, 3]
+        let fresh = DynTScale::new(vec![3, 5], vec![1, 1]).unwrap();
+        assert_eq!(stepped, fresh);
+        assert_eq!(format!("{stepped:?}"), "DynTScale { array: [3, 5], weight: [1, 1] }");
+        assert_ne!(stepped, DynTScale::new(vec![3, 5], vec![1, 2]).unwrap());
+        assert_ne!(stepped, DynTScale::new(vec![5, 3], vec![1, 1]).unwrap());
+    }
+}
@@ -0,0 +1,429 @@
+//! Estimate the weights of a t-scale sequence from observed data.
+//!
+//! Every observation after the first C gives one equation
+//!
+//! at = β1*a{t-1} + β2*a{t-2} + ... + βC*a{t-C} + εt
+//!
+//! and the weights are chosen to minimize Σ εt², optionally with all βi >= 0.
+//!
+//! For exact data, e.g. integer or modular sequences, [`berlekamp_massey`] finds the
+//! shortest recurrence that generates the terms instead.
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+use crate::tscale_sequence::TScale;
+
+/// Coordinate descent sweeps of the non-negative fit
+const MAX_SWEEPS: usize = 10000;
+
+/// Why the weights could not be fitted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FitError {
+    /// at least `needed` observations are required, but only `found` were given
+    NotEnoughData { needed: usize, found: usize },
+    /// the observation at `index` is NaN or infinite
+    NonFinite { index: usize },
+    /// the observations do not determine the weights, e.g. they are all 0
+    Singular,
+    /// the non-negative fit did not converge within `sweeps` sweeps
+    NotConverged { sweeps: usize },
+}
+
+impl Display for FitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotEnoughData { needed, found } => {
+                write!(f, "at least {needed} observations are needed, found {found}")
+            }
+            Self::NonFinite { index } => write!(f, "observation {index} is not finite"),
+            Self::Singular => write!(f, "the observations do not determine the weights"),
+            Self::NotConverged { sweeps } => {
+                write!(f, "the fit did not converge after {sweeps} sweeps")
+            }
+        }
+    }
+}
+
+impl Error for FitError {}
+
+/// Fitted weights and residual statistics
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fit<const C: usize> {
+    /// the weights, in the order of [`TScale::new_with_config`]
+    pub weight: [f64; C],
+    /// the last C observations, in ascending order
+    pub array: [f64; C],
+    /// number of equations, i.e. observations after the first C
+    pub samples: usize,
+    /// residual sum of squares Σ εt²
+    pub rss: f64,
+    /// root mean square error
+    pub rmse: f64,
+    /// coefficient of determination, 1 is a perfect fit
+    pub r_squared: f64,
+}
+
+impl<const C: usize> Fit<C> {
+    /// a sequence that continues from the last C observations with the fitted weights
+    ///
+    /// Its iterators yield the last C observations first, then the forecast.
+    pub const fn tscale(&self) -> TScale<f64, C> {
+        TScale::new_with_config(self.array, self.weight)
+    }
+}
+
+/// Ordinary least squares fit of the weights of order C
+pub fn fit_weights<const C: usize>(observations: &[f64]) -> Result<Fit<C>, FitError> {
+    let (matrix, rhs) = normal_equations::<C>(observations)?;
+    let weight = solve(matrix, rhs).ok_or(FitError::Singular)?;
+    Ok(statistics(observations, weight))
+}
+
+/// Least squares fit of the weights of order C, constrained to βi >= 0
+///
+/// Fails with [`FitError::NotConverged`] if the weights still change after 10000
+/// sweeps, e.g. for nearly collinear observations.
+pub fn fit_non_negative_weights<const C: usize>(
+    observations: &[f64],
+) -> Result<Fit<C>, FitError> {
+    let (matrix, rhs) = normal_equations::<C>(observations)?;
+    if (0..C).all(|i| matrix[i][i] == 0.0) {
+        return Err(FitError::Singular);
+    }
+    let weight = coordinate_descent(&matrix, &rhs, MAX_SWEEPS)?;
+    Ok(statistics(observations, weight))
+}
+
+/// Projected coordinate descent on ½βᵀAβ - bᵀβ, constrained to βi >= 0
+fn coordinate_descent<const C: usize>(
+    matrix: &[[f64; C]; C],
+    rhs: &[f64; C],
+    max_sweeps: usize,
+) -> Result<[f64; C], FitError> {
+    let mut weight = [0.0; C];
+    for _ in 0..max_sweeps {
+        let mut change = 0.0_f64;
+        for i in 0..C {
+            if matrix[i][i] == 0.0 {
+                continue;
+            }
+            let others = (0..C)
+                .filter(|j| *j != i)
+                .fold(rhs[i], |sum, j| (-matrix[i][j]).mul_add(weight[j], sum));
+            let value = (others / matrix[i][i]).max(0.0);
+            change = change.max((value - weight[i]).abs());
+            weight[i] = value;
+        }
+        if change <= 1e-14 * weight.iter().fold(1.0_f64, |max, w| max.max(w.abs())) {
+            return Ok(weight);
+        }
+    }
+    Err(FitError::NotConverged { sweeps: max_sweeps })
+}
+
+/// The shortest weights β1..βL such that at = Σ βi*a{t-i} holds for every term
+///
+/// This is the Berlekamp–Massey algorithm over f64, so it is meant for sequences that
+/// follow a recurrence exactly: discrepancies below 1e-9 of the largest term count as 0.
+/// The result is only unique if there are at least 2L terms.
+pub fn berlekamp_massey(terms: &[f64]) -> Vec<f64> {
+    let tolerance = 1e-9 * terms.iter().fold(0.0_f64, |max, a| max.max(a.abs()));
+    // connection polynomial 1 + c1*x + ... + cL*x^L, so βi = -ci
+    let mut connection = vec![1.0_f64];
+    let mut previous = vec![1.0];
+    let mut previous_discrepancy = 1.0;
+    let mut length = 0;
+    let mut shift = 1;
+    for n in 0..terms.len() {
+        let discrepancy = (1..=length).fold(terms[n], |sum, i| {
+            connection[i].mul_add(terms[n - i], sum)
+        });
+        if discrepancy.abs() <= tolerance {
+            shift += 1;
+            continue;
+        }
+        let factor = discrepancy / previous_discrepancy;
+        let saved = connection.clone();
+        if connection.len() < previous.len() + shift {
+            connection.resize(previous.len() + shift, 0.0);
+        }
+        for (i, b) in previous.iter().enumerate() {
+            connection[i + shift] = (-factor).mul_add(*b, connection[i + shift]);
+        }
+        if 2 * length <= n {
+            length = n + 1 - length;
+            previous = saved;
+            previous_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            shift += 1;
+        }
+    }
+    connection.resize(length + 1, 0.0);
+    connection[1..].iter().map(|c| -c).collect()
+}
+
+/// [`berlekamp_massey`] over the integers modulo a prime `modulus`
+///
+/// The terms are reduced modulo `modulus`, and the weights are returned modulo it.
+/// Every modulus up to `u64::MAX` works, but it must be prime: the inverses come from
+/// Fermat's little theorem, so a composite modulus gives wrong weights.
+///
+/// # Panics
+///
+/// Panics if `modulus` is smaller than 2.
+pub fn berlekamp_massey_mod(terms: &[u64], modulus: u64) -> Vec<u64> {
+    assert!(modulus >= 2, "the modulus must be a prime, found {modulus}");
+    let reduce = |a: u128| (a % u128::from(modulus)) as u64;
+    let mul = |a: u64, b: u64| reduce(u128::from(a) * u128::from(b));
+    let add = |a: u64, b: u64| reduce(u128::from(a) + u128::from(b));
+    // b < modulus, so modulus - b does not wrap
+    let sub = |a: u64, b: u64| add(a, modulus - b);
+    let inverse = |a: u64| {
+        // Fermat's little theorem, a^(p-2)
+        let (mut result, mut base, mut exponent) = (1, a, modulus - 2);
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = mul(result, base);
+            }
+            base = mul(base, base);
+            exponent >>= 1;
+        }
+        result
+    };
+    let mut connection = vec![1];
+    let mut previous = vec![1];
+    let mut previous_discrepancy = 1;
+    let mut length = 0;
+    let mut shift = 1;
+    for n in 0..terms.len() {
+        let discrepancy = (1..=length).fold(terms[n] % modulus, |sum, i| {
+            add(sum, mul(connection[i], terms[n - i]))
+        });
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+        let factor = mul(discrepancy, inverse(previous_discrepancy));
+        let saved = connection.clone();
+        if connection.len() < previous.len() + shift {
+            connection.resize(previous.len() + shift, 0);
+        }
+        for (i, b) in previous.iter().enumerate() {
+            connection[i + shift] = sub(connection[i + shift], mul(factor, *b));
+        }
+        if 2 * length <= n {
+            length = n + 1 - length;
+            previous = saved;
+            previous_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            shift += 1;
+        }
+    }
+    connection.resize(length + 1, 0);
+    connection[1..].iter().map(|c| sub(0, *c)).collect()
+}
+
+/// Identify the terms as a t-scale sequence of order at most C
+///
+/// Returns a sequence that starts with the first C terms and continues with the
+/// recurrence found by [`berlekamp_massey`], padded with zero weights. Returns `None` if
+/// the shortest recurrence is longer than C, or if there are too few terms to be sure
+/// (fewer than twice its length, or fewer than C).
+pub fn find_recurrence<const C: usize>(terms: &[f64]) -> Option<TScale<f64, C>> {
+    let weight = berlekamp_massey(terms);
+    if weight.len() > C || terms.len() < C.max(2 * weight.len()) {
+        return None;
+    }
+    Some(TScale::new_with_config(
+        std::array::from_fn(|i| terms[i]),
+        std::array::from_fn(|i| weight.get(i).copied().unwrap_or(0.0)),
+    ))
+}
+
+/// The lagged rows of the design matrix: for each t >= C, (a{t-1}, ..., a{t-C}) and at
+fn rows<const C: usize>(observations: &[f64]) -> impl Iterator<Item = ([f64; C], f64)> + '_ {
+    (C..observations.len())
+        .map(|t| (std::array::from_fn(|i| observations[t - 1 - i]), observations[t]))
+}
+
+/// XᵀX and Xᵀy
+fn normal_equations<const C: usize>(
+    observations: &[f64],
+) -> Result<([[f64; C]; C], [f64; C]), FitError> {
+    if C == 0 || observations.len() < 2 * C {
+        return Err(FitError::NotEnoughData {
+            needed: 2 * C.max(1),
+            found: observations.len(),
+        });
+    }
+    if let Some(index) = observations.iter().position(|a| !a.is_finite()) {
+        return Err(FitError::NonFinite { index });
+    }
+    let mut matrix = [[0.0; C]; C];
+    let mut rhs = [0.0; C];
+    for (row, target) in rows::<C>(observations) {
+        for i in 0..C {
+            for j in 0..C {
+                matrix[i][j] = row[i].mul_add(row[j], matrix[i][j]);
+            }
+            rhs[i] = row[i].mul_add(target, rhs[i]);
+        }
+    }
+    Ok((matrix, rhs))
+}
+
+/// Gaussian elimination with partial pivoting, `None` if the matrix is singular
+fn solve<const C: usize>(mut matrix: [[f64; C]; C], mut rhs: [f64; C]) -> Option<[f64; C]> {
+    let scale = (0..C).fold(0.0_f64, |max, i| max.max(matrix[i][i].abs()));
+    for col in 0..C {
+        let pivot = (col..C)
+            .max_by(|a, b| matrix[*a][col].abs().total_cmp(&matrix[*b][col].abs()))?;
+        if matrix[pivot][col].abs() <= 1e-12 * scale {
+            return None;
+        }
+        matrix.swap(col, pivot);
+        rhs.swap(col, pivot);
+        let pivot_row = matrix[col];
+        for row in col + 1..C {
+            let factor = matrix[row][col] / pivot_row[col];
+            matrix[row]
+                .iter_mut()
+                .zip(pivot_row)
+                .skip(col)
+                .for_each(|(value, pivot)| *value = (-factor).mul_add(pivot, *value));
+            rhs[row] = (-factor).mul_add(rhs[col], rhs[row]);
+        }
+    }
+    let mut solution = [0.0; C];
+    for row in (0..C).rev() {
+        let sum = (row + 1..C)
+            .fold(rhs[row], |sum, k| (-matrix[row][k]).mul_add(solution[k], sum));
+        solution[row] = sum / matrix[row][row];
+    }
+    Some(solution)
+}
+
+fn statistics<const C: usize>(observations: &[f64], weight: [f64; C]) -> Fit<C> {
+    let samples = observations.len() - C;
+    let mean = observations[C..].iter().sum::<f64>() / samples as f64;
+    let (rss, tss) = rows::<C>(observations).fold((0.0, 0.0), |(rss, tss), (row, target)| {
+        let predicted = row
+            .iter()
+            .zip(weight.iter())
+            .fold(0.0, |sum, (a, beta)| a.mul_add(*beta, sum));
+        let residual = target - predicted;
+        let deviation = target - mean;
+        (residual.mul_add(residual, rss), deviation.mul_add(deviation, tss))
+    });
+    let r_squared = if tss == 0.0 {
+        if rss == 0.0 {
+            1.0
+        } else {
+            0.0
+        }
+    } else {
+        1.0 - rss / tss
+    };
+    Fit {
+        weight,
+        array: std::array::from_fn(|i| observations[observations.len() - C + i]),
+        samples,
+        rss,
+        rmse: (rss / samples as f64).sqrt(),
+        r_squared,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approximately::ApproxEq;
+
+    use super::*;
+
+    #[test]
+    fn test_fit() {
+        let weight = [0.5, 0.3, 0.4];
+        let observations = TScale::new_with_config([1.0, 2.0, 3.0], weight)
+            .into_iter()
+            .take(30)
+            .collect::<Vec<_>>();
+        let fit = fit_weights::<3>(&observations).unwrap();
+        fit.weight
+            .iter()
+            .zip(weight.iter())
+            .for_each(|(fitted, beta)| fitted.assert_approx(*beta));
+        fit.rss.assert_approx(0.0);
+        fit.r_squared.assert_approx(1.0);
+        fit.tscale()
+            .into_iter()
+            .nth(3)
+            .unwrap()
+            .assert_approx(TScale::new_with_config([1.0, 2.0, 3.0], weight).nth_term(30));
+
+        assert_eq!(
+            fit_weights::<3>(&observations[..5]),
+            Err(FitError::NotEnoughData { needed: 6, found: 5 })
+        );
+        assert_eq!(fit_weights::<2>(&[0.0; 10]), Err(FitError::Singular));
+    }
+
+    #[test]
+    fn test_berlekamp_massey() {
+        // Fibonacci sequence
+        let terms = TScale::new_with_config([0.0, 1.0], [1.0, 1.0])
+            .into_iter()
+            .take(20)
+            .collect::<Vec<_>>();
+        let weight = berlekamp_massey(&terms);
+        assert_eq!(weight.len(), 2);
+        weight.iter().for_each(|beta| beta.assert_approx(1.0));
+
+        let tscale = find_recurrence::<3>(&terms).unwrap();
+        tscale.nth_term(30).assert_approx(832_040.0);
+
+        // squares: at = 3a{t-1} - 3a{t-2} + a{t-3}
+        let squares = (0..10).map(|t| f64::from(t * t)).collect::<Vec<_>>();
+        let weight = berlekamp_massey(&squares);
+        weight
+            .iter()
+            .zip([3.0, -3.0, 1.0])
+            .for_each(|(fitted, beta)| fitted.assert_approx(beta));
+        assert!(find_recurrence::<2>(&squares).is_none());
+
+        let modulus = 1_000_000_007;
+        let terms = [1, 1, 2, 3, 5, 8, 13, 21];
+        assert_eq!(berlekamp_massey_mod(&terms, modulus), vec![1, 1]);
+        let terms = [2, 0, 1, 2, 0, 1, 2, 0];
+        assert_eq!(berlekamp_massey_mod(&terms, modulus), vec![0, 0, 1]);
+        // the largest 64 bit prime, at = -a{t-1}
+        let modulus = u64::MAX - 58;
+        let terms = [1, modulus - 1, 1, modulus - 1, 1, modulus - 1];
+        assert_eq!(berlekamp_massey_mod(&terms, modulus), vec![modulus - 1]);
+    }
+
+    #[test]
+    fn test_non_negative_fit() {
+        let observations = TScale::new_with_config([1.0, 1.0], [1.2, -0.1])
+            .into_iter()
+            .take(20)
+            .collect::<Vec<_>>();
+        fit_weights::<2>(&observations).unwrap().weight[1].assert_approx(-0.1);
+
+        let fit = fit_non_negative_weights::<2>(&observations).unwrap();
+        assert_eq!(fit.weight[1], 0.0);
+        assert!(fit.weight[0] > 1.0);
+        assert!(fit.rss > 0.0);
+
+        let (matrix, rhs) = normal_equations::<2>(&observations).unwrap();
+        assert_eq!(
+            coordinate_descent(&matrix, &rhs, 1),
+            Err(FitError::NotConverged { sweeps: 1 })
+        );
+    }
+}
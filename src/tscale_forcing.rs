@@ -0,0 +1,198 @@
+//! Inhomogeneous t-scale sequences.
+//!
+//! A forcing term u adds a value to every step, e.g. immigration or emigration:
+//!
+//! at = β1*a{t-1} + β2*a{t-2} + ... + βn*a{t-n} + ut
+//!
+//! ```rust
+//! # use tscale_sequence::{tscale_forcing::Constant, tscale_sequence::TScale};
+//! let mut tscale = TScale::new_with_config([100.0, 100.0], [0.5, 0.3])
+//!     .with_forcing(Constant(20.0));
+//! let value = tscale.step();
+//! ```
+//!
+//! For a constant forcing term,
+//! [`constant_forcing_equilibrium`](crate::tscale_rate::constant_forcing_equilibrium)
+//! gives the value the sequence settles at.
+
+use std::ops::{Add, Mul};
+
+use crate::{
+    tscale_jump,
+    tscale_sequence::{Bounded, RingBuffer, TScale},
+};
+
+/// A source of the forcing term ut
+pub trait Forcing<T> {
+    /// the value added at `tick`, which counts the steps starting from 0
+    fn force(&mut self, tick: usize) -> T;
+}
+
+/// The same value every tick
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Constant<T>(pub T);
+
+impl<T: Clone> Forcing<T> for Constant<T> {
+    fn force(&mut self, _tick: usize) -> T {
+        self.0.clone()
+    }
+}
+
+impl<T, F> Forcing<T> for F
+where
+    F: FnMut(usize) -> T,
+{
+    fn force(&mut self, tick: usize) -> T {
+        self(tick)
+    }
+}
+
+/// The values of an iterator, one per tick
+///
+/// Once the iterator ends the forcing term is `T::default()`.
+#[derive(Debug, Clone)]
+pub struct FromIter<I>(pub I);
+
+impl<T, I> Forcing<T> for FromIter<I>
+where
+    T: Default,
+    I: Iterator<Item = T>,
+{
+    fn force(&mut self, _tick: usize) -> T {
+        self.0.next().unwrap_or_default()
+    }
+}
+
+impl<T, const C: usize> TScale<T, C> {
+    /// add `forcing` to every step from now on
+    pub const fn with_forcing<F: Forcing<T>>(self, forcing: F) -> ForcedTScale<T, C, F> {
+        ForcedTScale {
+            tscale: self,
+            forcing,
+            tick: 0,
+        }
+    }
+}
+
+/// A [`TScale`] with a forcing term, created by [`TScale::with_forcing`]
+pub struct ForcedTScale<T, const C: usize, F> {
+    tscale: TScale<T, C>,
+    forcing: F,
+    tick: usize,
+}
+
+impl<T, const C: usize, F> ForcedTScale<T, C, F> {
+    /// the number of steps taken so far, i.e. the tick of the next forcing term
+    pub const fn tick(&self) -> usize {
+        self.tick
+    }
+
+    /// the sequence without the forcing term
+    pub fn into_inner(self) -> TScale<T, C> {
+        self.tscale
+    }
+
+    /// into iterator
+    ///
+    /// It generates 10000 values by default, use [`Bounded::with_len`] or
+    /// [`Bounded::unbounded`] to change that.
+    pub fn iter(&mut self) -> Bounded<ForcedTScaleIter<'_, T, C, F>> {
+        Bounded::new(ForcedTScaleIter { tscale: self })
+    }
+}
+
+impl<T: Clone, const C: usize, F> ForcedTScale<T, C, F> {
+    /// the last C values, oldest first
+    pub fn current_window(&self) -> [T; C] {
+        self.tscale.current_window()
+    }
+}
+
+impl<T, const C: usize, F> ForcedTScale<T, C, F>
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+    F: Forcing<T>,
+{
+    /// advance one tick and return the new value
+    pub fn step(&mut self) -> T {
+        self.step_ring();
+        self.tscale.array[(self.tscale.head + C - 1) % C].clone()
+    }
+
+    /// advance one tick and return the oldest value
+    fn step_ring(&mut self) -> T {
+        let forcing = self.forcing.force(self.tick);
+        self.tick += 1;
+        let TScale {
+            array,
+            weight,
+            head,
+        } = &mut self.tscale;
+        let oldest = tscale_jump::step_ring(array, weight, head);
+        let newest = (*head + C - 1) % C;
+        array[newest] = array[newest].clone() + forcing;
+        oldest
+    }
+}
+
+/// Recursive iterator with a forcing term
+pub struct ForcedTScaleIter<'a, T, const C: usize, F> {
+    tscale: &'a mut ForcedTScale<T, C, F>,
+}
+
+impl<'a, T, const C: usize, F> RingBuffer for ForcedTScaleIter<'a, T, C, F>
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+    F: Forcing<T>,
+{
+    type Value = T;
+
+    fn step(&mut self) -> T {
+        self.tscale.step_ring()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approximately::ApproxEq;
+
+    use super::*;
+    use crate::tscale_rate::{constant_forcing_equilibrium, Equilibrium};
+
+    #[test]
+    fn test_forcing() {
+        let array = [100.0, 100.0];
+        let weight = [0.5, 0.3];
+        let mut tscale = TScale::new_with_config(array, weight).with_forcing(Constant(30.0));
+        // 0.5*100 + 0.3*100 + 30
+        tscale.step().assert_approx(110.0);
+        let Ok(Equilibrium::Fixed(equilibrium)) = constant_forcing_equilibrium(&weight, 30.0)
+        else {
+            panic!("β sums to 0.8")
+        };
+        tscale.iter().nth(500).unwrap().assert_approx(equilibrium);
+        assert_eq!(tscale.tick(), 502);
+
+        // a closure and an iterator with the same values agree
+        let mut by_closure = TScale::new_with_config(array, weight)
+            .with_forcing(|tick: usize| [10.0, -5.0][tick % 2]);
+        let mut by_iter = TScale::new_with_config(array, weight)
+            .with_forcing(FromIter([10.0, -5.0].into_iter().cycle()));
+        for _ in 0..50 {
+            by_closure.step().assert_approx(by_iter.step());
+        }
+
+        // once the iterator ends, there is no forcing term
+        let mut tscale =
+            TScale::new_with_config(array, weight).with_forcing(FromIter(None.into_iter()));
+        let mut expected = TScale::new_with_config(array, weight);
+        tscale.step().assert_approx(expected.step());
+
+        // forced simulations can run past the default 10000 values
+        let mut tscale = TScale::new_with_config(array, weight).with_forcing(Constant(30.0));
+        assert_eq!(tscale.iter().len(), 10_000);
+        assert_eq!(tscale.iter().unbounded().take(20_000).count(), 20_000);
+        tscale.iter().nth(4).unwrap().assert_approx(equilibrium);
+        assert_eq!(tscale.tick(), 20_005);
+    }
+}
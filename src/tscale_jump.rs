@@ -0,0 +1,334 @@
+//! Jump ahead in a t-scale sequence without stepping through every term.
+//!
+//! Stepping an iterator costs O(C) per term, so reaching term n costs O(nC).
+//! Two faster strategies are available, see [`JumpStrategy`].
+//!
+//! Neither strategy needs a "one" value for `T`: the jump is done in blocks of C terms
+//! (whose coefficients only contain the weights) and the remaining `n % C` terms are
+//! stepped directly.
+
+use std::ops::{Add, Mul};
+
+/// How to jump ahead in the sequence.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JumpStrategy {
+    /// Repeated squaring of the companion matrix, O(C³ log n).
+    #[default]
+    Matrix,
+    /// Kitamasa's method: reduce x^n modulo the characteristic polynomial, O(C² log n).
+    ///
+    /// Prefer it for large C.
+    Kitamasa,
+}
+
+/// Dot product of two equally long slices, starting from `T::default()`.
+fn dot<T>(left: &[T], right: &[T]) -> T
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    left.iter()
+        .zip(right.iter())
+        .fold(T::default(), |acc, (a, b)| acc + a.clone() * b.clone())
+}
+
+/// Advance `array` by one term in place.
+fn step<T>(array: &mut [T], weight: &[T])
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    let last_value = array
+        .iter()
+        .zip(weight.iter().rev())
+        .fold(T::default(), |acc, (a, b)| acc + a.clone() * b.clone());
+    array.rotate_left(1);
+    array[array.len() - 1] = last_value;
+}
+
+/// Advance a ring buffer by one term and return the oldest value
+///
+/// The logical order of `array` starts at `head`, so only the slot of the oldest value
+/// is written instead of shifting every value.
+pub(crate) fn step_ring<T>(array: &mut [T], weight: &[T], head: &mut usize) -> T
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    let (newer, older) = array.split_at(*head);
+    let last_value = older
+        .iter()
+        .chain(newer.iter())
+        .zip(weight.iter().rev())
+        .fold(T::default(), |acc, (a, b)| acc + a.clone() * b.clone());
+    let first_value = std::mem::replace(&mut array[*head], last_value);
+    *head = (*head + 1) % array.len();
+    first_value
+}
+
+/// Advance a ring buffer by `n` terms
+///
+/// Stepping costs O(nC) and jumping with [`JumpStrategy::Kitamasa`] O(C² log n),
+/// so short distances are stepped.
+pub(crate) fn skip_ring<T>(array: &mut [T], weight: &[T], head: &mut usize, n: usize)
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    let log = (usize::BITS - n.leading_zeros()) as usize;
+    if n <= array.len().saturating_mul(log) {
+        for _ in 0..n {
+            step_ring(array, weight, head);
+        }
+        return;
+    }
+    // the jump works on the logical order, oldest first
+    array.rotate_left(*head);
+    *head = 0;
+    advance_with(array, weight, n, JumpStrategy::Kitamasa);
+}
+
+/// Multiply the coefficients of x^m by x, reducing modulo the characteristic polynomial.
+///
+/// `coefficients[j]` is the factor of `a{t+j}` in `a{t+m}`, so the result holds the
+/// factors of `a{t+m+1}`.
+fn shift_coefficients<T>(coefficients: &[T], weight: &[T]) -> Vec<T>
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    let top = coefficients[coefficients.len() - 1].clone();
+    weight
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(j, w)| {
+            let carried = if j == 0 {
+                T::default()
+            } else {
+                coefficients[j - 1].clone()
+            };
+            carried + top.clone() * w.clone()
+        })
+        .collect()
+}
+
+/// The C-th power of the companion matrix, in row-major order.
+///
+/// Row i holds the coefficients of `a{t+C+i}` in terms of `a{t}..a{t+C-1}`.
+fn companion_block<T>(weight: &[T]) -> Vec<T>
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    let mut row: Vec<T> = weight.iter().rev().cloned().collect();
+    let mut matrix = Vec::with_capacity(weight.len() * weight.len());
+    for _ in 0..weight.len() {
+        let next = shift_coefficients(&row, weight);
+        matrix.append(&mut row);
+        row = next;
+    }
+    matrix
+}
+
+fn mul_matrix<T>(left: &[T], right: &[T], c: usize) -> Vec<T>
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    let mut product = Vec::with_capacity(c * c);
+    for row in left.chunks(c) {
+        for col in 0..c {
+            product.push(
+                row.iter()
+                    .enumerate()
+                    .fold(T::default(), |acc, (k, a)| {
+                        acc + a.clone() * right[k * c + col].clone()
+                    }),
+            );
+        }
+    }
+    product
+}
+
+fn mul_vector<T>(matrix: &[T], vector: &[T]) -> Vec<T>
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    matrix
+        .chunks(vector.len())
+        .map(|row| dot(row, vector))
+        .collect()
+}
+
+/// Multiply two coefficient vectors modulo the characteristic polynomial.
+fn mul_coefficients<T>(left: &[T], right: &[T], weight: &[T]) -> Vec<T>
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    let c = weight.len();
+    let mut product = vec![T::default(); 2 * c - 1];
+    for (i, a) in left.iter().enumerate() {
+        for (j, b) in right.iter().enumerate() {
+            product[i + j] = product[i + j].clone() + a.clone() * b.clone();
+        }
+    }
+    // x^k = x^(k-C) * x^C, and x^C is the weight reversed
+    for k in (c..2 * c - 1).rev() {
+        let top = std::mem::take(&mut product[k]);
+        for (j, w) in weight.iter().rev().enumerate() {
+            product[k - c + j] = product[k - c + j].clone() + top.clone() * w.clone();
+        }
+    }
+    product.truncate(c);
+    product
+}
+
+fn advance_blocks_matrix<T>(array: &mut [T], weight: &[T], mut blocks: usize)
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    let c = weight.len();
+    let mut power = companion_block(weight);
+    let mut state = array.to_vec();
+    loop {
+        if blocks & 1 == 1 {
+            state = mul_vector(&power, &state);
+        }
+        blocks >>= 1;
+        if blocks == 0 {
+            break;
+        }
+        power = mul_matrix(&power, &power, c);
+    }
+    array.clone_from_slice(&state);
+}
+
+fn advance_blocks_kitamasa<T>(array: &mut [T], weight: &[T], mut blocks: usize)
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    let mut power: Vec<T> = weight.iter().rev().cloned().collect();
+    let mut coefficients: Option<Vec<T>> = None;
+    loop {
+        if blocks & 1 == 1 {
+            coefficients = Some(coefficients.map_or_else(
+                || power.clone(),
+                |coefficients| mul_coefficients(&coefficients, &power, weight),
+            ));
+        }
+        blocks >>= 1;
+        if blocks == 0 {
+            break;
+        }
+        power = mul_coefficients(&power, &power, weight);
+    }
+    let mut coefficients = coefficients.expect("blocks must be greater than 0");
+    let state = (0..weight.len())
+        .map(|_| {
+            let value = dot(&coefficients, array);
+            coefficients = shift_coefficients(&coefficients, weight);
+            value
+        })
+        .collect::<Vec<_>>();
+    array.clone_from_slice(&state);
+}
+
+/// Advance `array` by `steps` terms in place, using [`JumpStrategy::Matrix`].
+///
+/// `array` and `weight` follow the layout of [`TScale`](crate::tscale_sequence::TScale):
+/// `array` is in ascending order and `weight` starts with the most recent coefficient.
+///
+/// # Panics
+///
+/// Panics if `array` and `weight` have different lengths.
+pub fn advance<T>(array: &mut [T], weight: &[T], steps: usize)
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    advance_with(array, weight, steps, JumpStrategy::Matrix);
+}
+
+/// Advance `array` by `steps` terms in place, using the given strategy.
+///
+/// # Panics
+///
+/// Panics if `array` and `weight` have different lengths.
+pub fn advance_with<T>(array: &mut [T], weight: &[T], steps: usize, strategy: JumpStrategy)
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    let c = weight.len();
+    assert_eq!(array.len(), c, "array and weight must have the same length");
+    if c == 0 {
+        return;
+    }
+    let blocks = steps / c;
+    if blocks > 0 {
+        match strategy {
+            JumpStrategy::Matrix => advance_blocks_matrix(array, weight, blocks),
+            JumpStrategy::Kitamasa => advance_blocks_kitamasa(array, weight, blocks),
+        }
+    }
+    (0..steps % c).for_each(|_| step(array, weight));
+}
+
+/// Compute the n-th term of the sequence, where `array[0]` is term 0,
+/// using [`JumpStrategy::Matrix`].
+///
+/// # Panics
+///
+/// Panics if `array` is empty or if `array` and `weight` have different lengths.
+pub fn nth_term<T>(array: &[T], weight: &[T], n: usize) -> T
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    nth_term_with(array, weight, n, JumpStrategy::Matrix)
+}
+
+/// Compute the n-th term of the sequence, using the given strategy.
+///
+/// # Panics
+///
+/// Panics if `array` is empty or if `array` and `weight` have different lengths.
+pub fn nth_term_with<T>(array: &[T], weight: &[T], n: usize, strategy: JumpStrategy) -> T
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    assert!(!array.is_empty(), "C must be greater than 0");
+    let c = array.len();
+    if n < c {
+        return array[n].clone();
+    }
+    let mut state = array.to_vec();
+    advance_with(&mut state, weight, n - (c - 1), strategy);
+    state[c - 1].clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nth_term() {
+        // Fibonacci sequence
+        let array = [0u64, 1];
+        let weight = [1u64, 1];
+        assert_eq!(nth_term(&array, &weight, 0), 0);
+        assert_eq!(nth_term(&array, &weight, 10), 55);
+        assert_eq!(nth_term(&array, &weight, 90), 2_880_067_194_370_816_120);
+
+        let array = [1i64, 2, 3];
+        let weight = [2i64, 0, -1];
+        let mut stepped = array;
+        for n in 0..40 {
+            assert_eq!(nth_term(&array, &weight, n), stepped[0]);
+            step(&mut stepped, &weight);
+        }
+    }
+
+    #[test]
+    fn test_strategy() {
+        let array = [3i64, -1, 4, 1, 5];
+        let weight = [1i64, 0, -1, 1, 1];
+        for n in [0, 4, 5, 9, 10, 11, 37, 64, 65] {
+            assert_eq!(
+                nth_term_with(&array, &weight, n, JumpStrategy::Kitamasa),
+                nth_term_with(&array, &weight, n, JumpStrategy::Matrix)
+            );
+        }
+    }
+}
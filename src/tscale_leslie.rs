@@ -0,0 +1,163 @@
+//! Age-structured population model with survival rates.
+//!
+//! [`TScale`](crate::tscale_sequence::TScale) assumes every newborn lives through all C
+//! age classes. [`Leslie`] adds the probability to survive each age class, and tracks
+//! the population of every age class instead of only the births:
+//!
+//! ni(t+1) = s{i-1}*n{i-1}(t), n0(t+1) = Σ fi*ni(t)
+//!
+//! where fi is the fecundity and si the survival probability of age class i. The births
+//! follow a t-scale sequence with the effective weights fi*s0*...*s{i-1}, so with all
+//! survival rates 1 it is exactly the [`TScale`](crate::tscale_sequence::TScale) with
+//! the fecundity as weights.
+
+use crate::tscale_rate::{try_compute_limit_rate, RateError};
+
+/// Leslie matrix model, age class 0 are the newborns
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leslie<const C: usize> {
+    /// births per individual of each age class, the `weight` of a t-scale sequence
+    fecundity: [f64; C],
+    /// probability to survive from age class i to i+1
+    survival: [f64; C],
+    /// population of each age class, youngest first
+    population: [f64; C],
+}
+
+impl<const C: usize> Leslie<C> {
+    /// create a model from the population of each age class, youngest first
+    ///
+    /// `survival[i]` is the probability to survive from age class i to i+1, so
+    /// `survival[0]` applies to the newborns. The oldest age class leaves the model
+    /// after one tick, so `survival[C - 1]` has no effect.
+    pub const fn new(population: [f64; C], fecundity: [f64; C], survival: [f64; C]) -> Self {
+        Self {
+            fecundity,
+            survival,
+            population,
+        }
+    }
+
+    /// advance one tick and return the births
+    pub fn step(&mut self) -> f64 {
+        // oldest first like the t-scale sequence, so the rounding is the same
+        let births = self
+            .fecundity
+            .iter()
+            .zip(&self.population)
+            .rev()
+            .map(|(fecundity, population)| population * fecundity)
+            .sum::<f64>();
+        self.population.rotate_right(1);
+        self.population[0] = births;
+        self.population[1..]
+            .iter_mut()
+            .zip(&self.survival)
+            .for_each(|(population, survival)| *population *= survival);
+        births
+    }
+
+    /// population of each age class, youngest first
+    pub const fn population(&self) -> &[f64; C] {
+        &self.population
+    }
+
+    /// the sum of all age classes
+    pub fn total(&self) -> f64 {
+        self.population.iter().sum()
+    }
+
+    /// births of the last tick, i.e. age class 0
+    pub const fn births(&self) -> f64 {
+        self.population[0]
+    }
+
+    /// the weights fi*s0*...*s{i-1} of the t-scale sequence of the births
+    pub fn effective_weights(&self) -> [f64; C] {
+        let mut survivorship = 1.0;
+        std::array::from_fn(|i| {
+            let weight = self.fecundity[i] * survivorship;
+            survivorship *= self.survival[i];
+            weight
+        })
+    }
+
+    /// the dominant eigenvalue of the Leslie matrix, the growth per tick in the long run
+    pub fn dominant_eigenvalue(&self) -> Result<f64, RateError> {
+        try_compute_limit_rate(&self.effective_weights())
+    }
+
+    /// the share of each age class the population converges to, youngest first
+    ///
+    /// It is the right eigenvector of the dominant eigenvalue λ, s0*...*s{i-1}*λ^-i,
+    /// normalized to sum 1.
+    pub fn stable_age_distribution(&self) -> Result<[f64; C], RateError> {
+        let rate = self.dominant_eigenvalue()?;
+        let mut survivorship = 1.0;
+        let mut distribution = std::array::from_fn(|i| {
+            let share = survivorship * rate.powi(-(i as i32));
+            survivorship *= self.survival[i];
+            share
+        });
+        let sum = distribution.iter().sum::<f64>();
+        distribution.iter_mut().for_each(|share| *share /= sum);
+        Ok(distribution)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approximately::ApproxEq;
+
+    use super::*;
+    use crate::tscale_sequence::TScale;
+
+    #[test]
+    fn test_reduces_to_tscale() {
+        let array = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let weight = [0.4, 1.2, 0.3, 0.1, 0.0];
+        let mut population = array;
+        population.reverse();
+        let mut leslie = Leslie::new(population, weight, [1.0; 5]);
+        let mut tscale = TScale::new_with_config(array, weight);
+        for _ in 0..50 {
+            assert_eq!(leslie.step(), tscale.step());
+        }
+        let mut window = tscale.current_window();
+        window.reverse();
+        assert_eq!(leslie.population(), &window);
+        leslie
+            .dominant_eigenvalue()
+            .unwrap()
+            .assert_approx(TScale::new_with_config(array, weight).closed_form().dominant_root());
+    }
+
+    #[test]
+    fn test_survival() {
+        let fecundity = [0.0, 1.5, 1.2, 0.4];
+        let survival = [0.8, 0.7, 0.5, 0.0];
+        let mut leslie = Leslie::new([10.0, 10.0, 10.0, 10.0], fecundity, survival);
+        let expected = [0.0, 1.2, 0.8 * 0.7 * 1.2, 0.8 * 0.7 * 0.5 * 0.4];
+        for (weight, expected) in leslie.effective_weights().iter().zip(expected) {
+            weight.assert_approx(expected);
+        }
+        // 1.5*10 + 1.2*10 + 0.4*10
+        leslie.step().assert_approx(31.0);
+        for (population, expected) in leslie.population().iter().zip([31.0, 8.0, 7.0, 5.0]) {
+            population.assert_approx(expected);
+        }
+
+        for _ in 0..200 {
+            leslie.step();
+        }
+        let rate = leslie.dominant_eigenvalue().unwrap();
+        let total = leslie.total();
+        leslie.step();
+        (leslie.total() / total).assert_approx(rate);
+        let stable = leslie.stable_age_distribution().unwrap();
+        stable.iter().sum::<f64>().assert_approx(1.0);
+        for (population, share) in leslie.population().iter().zip(stable) {
+            (population / leslie.total()).assert_approx(share);
+        }
+    }
+}
@@ -1,7 +1,129 @@
 //! Used to calculate the ratio as time t approaches positive infinity.
 
+use std::{
+    cmp::Ordering,
+    error::Error,
+    f64::consts::TAU,
+    fmt::{self, Display},
+};
+
 use approximately::ApproxEq;
 
+use crate::{
+    complex::Complex,
+    tscale_jump,
+    tscale_solver::{RateSolver, DEFAULT_MAX_ITERATIONS},
+    tscale_stochastic::{Noise, SplitMix64},
+};
+
+const DURAND_KERNER_ITERATIONS: usize = 1000;
+/// Roots closer than this, relative to their modulus, are merged into a multiple root.
+///
+/// Numerical root finders only resolve a root of multiplicity m to about ε^(1/m),
+/// so this has to be much looser than the machine precision.
+const ROOT_MERGE_TOLERANCE: f64 = 1e-4;
+
+/// Why a rate could not be computed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RateError {
+    /// no beta was given, or the rank is 0
+    Empty,
+    /// the beta at `index` is negative
+    NegativeWeight { index: usize },
+    /// every beta is 0, so the sequence has no limit rate
+    AllZero,
+    /// the beta at `index` is NaN or infinite
+    NonFinite { index: usize },
+    /// the solver did not converge within `iterations` steps
+    NotConverged { iterations: usize },
+    /// the target rate is not a positive, finite number
+    InvalidTarget,
+    /// `index` is not a valid beta index
+    IndexOutOfRange { index: usize },
+    /// the target rate would need a negative beta
+    Unreachable,
+    /// a simulation needs at least `needed` replicates, but got `found`
+    TooFewReplicates { needed: usize, found: usize },
+    /// a simulation needs at least `needed` ticks, but got `found`
+    TooFewTicks { needed: usize, found: usize },
+    /// only `survivors` replicates did not die out, too few for an estimate
+    Extinct { survivors: usize },
+}
+
+impl Display for RateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "less than 1 beta is not allowed"),
+            Self::NegativeWeight { index } => write!(f, "beta {index} is negative"),
+            Self::AllZero => write!(f, "at least one beta must be greater than 0"),
+            Self::NonFinite { index } => write!(f, "beta {index} is not finite"),
+            Self::NotConverged { iterations } => {
+                write!(f, "the rate did not converge after {iterations} iterations")
+            }
+            Self::InvalidTarget => write!(f, "the target rate must be positive and finite"),
+            Self::IndexOutOfRange { index } => write!(f, "beta {index} does not exist"),
+            Self::Unreachable => write!(f, "the target rate needs a negative beta"),
+            Self::TooFewReplicates { needed, found } => {
+                write!(f, "at least {needed} replicates are needed, found {found}")
+            }
+            Self::TooFewTicks { needed, found } => {
+                write!(f, "at least {needed} ticks are needed, found {found}")
+            }
+            Self::Extinct { survivors } => {
+                write!(f, "only {survivors} replicates survived, at least 2 are needed")
+            }
+        }
+    }
+}
+
+impl Error for RateError {}
+
+/// How the limit rate responds to a change of one beta
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sensitivity {
+    /// ∂r/∂βi
+    pub derivative: f64,
+    /// (βi/r)*∂r/∂βi, the relative change of r per relative change of βi
+    pub elasticity: f64,
+}
+
+/// A particular solution of at = Σ βi*a{t-i} + u for a constant forcing term u
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Equilibrium {
+    /// at = a* for all t, where a* = u / (1 - Σ βi)
+    ///
+    /// The sequence converges to it if Σ βi < 1, otherwise it moves away from it.
+    Fixed(f64),
+    /// Σ βi = 1, so there is no fixed value, instead at = d*t grows by
+    /// d = u / Σ i*βi every tick
+    Drift(f64),
+}
+
+/// Estimate of the long-run growth in a random environment, see [`stochastic_growth_rate`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StochasticGrowth {
+    /// the estimate of log λs, the mean of the replicates
+    pub log_rate: f64,
+    /// the standard error of `log_rate`
+    pub standard_error: f64,
+    /// the 95% confidence interval of log λs
+    pub confidence_interval: (f64, f64),
+    /// the small-noise approximation of log λs by Tuljapurkar
+    pub tuljapurkar: f64,
+    /// the replicates that died out, i.e. every value became 0
+    ///
+    /// They are left out of the estimate, so it is the growth rate given survival.
+    pub extinct: usize,
+}
+
+/// A root of the characteristic polynomial x^n - β1*x^{n-1} - ... - βn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CharacteristicRoot {
+    pub value: Complex,
+    pub multiplicity: usize,
+}
+
 /// rank must be greater than 1
 /// 
 /// To handle the case where β = 1
@@ -9,59 +131,600 @@ use approximately::ApproxEq;
 /// The function is defined as:
 /// 
 /// at = a{t-1} + a{t-2} + ... + a{t-rank}
+///
+/// # Panics
+///
+/// Panics if [`try_compute_limit_normal_rate`] returns an error.
 pub fn compute_limit_normal_rate(rank: usize) -> f64 {
+    try_compute_limit_normal_rate(rank).unwrap_or_else(|error| panic!("{error}"))
+}
+
+/// Same as [`compute_limit_normal_rate`], but returns an error instead of panicking
+/// or iterating forever
+pub fn try_compute_limit_normal_rate(rank: usize) -> Result<f64, RateError> {
     if rank == 0 {
-        panic!("rank must be greater than 1");
+        return Err(RateError::Empty);
     }
     let rank = rank as f64;
     let fx = |x: f64| x + x.powf(-rank) - 2.0;
     let dfdx = |x: f64| rank.mul_add(-x.powf(-rank - 1.0), 1.0);
     let mut x = 2.0;
-    loop {
+    for iteration in 1..=DEFAULT_MAX_ITERATIONS {
         let x1 = x - fx(x) / dfdx(x);
+        if !x1.is_finite() {
+            return Err(RateError::NotConverged {
+                iterations: iteration,
+            });
+        }
         if x1.approx(x){
-            return x1;
+            return Ok(x1);
         }
         x = x1;
     }
+    Err(RateError::NotConverged {
+        iterations: DEFAULT_MAX_ITERATIONS,
+    })
 }
 
 /// The function is defined as:
 /// 
 /// at = β1*a{t-1} + β2*a{t-2} + ... + βn*a{t-n}
+///
+/// The ratio at/a{t-1} only converges to the returned rate if [`period`] is 1,
+/// otherwise the rate is the geometric mean of the ratios over one period.
+///
+/// Unlike [`try_compute_limit_rate`], negative betas are accepted, e.g. `[1.2, -0.1]`.
+/// Then Newton's method runs from 1 like before there was a bracketing solver, and it
+/// may find a root other than the dominant one, see [`characteristic_roots`].
+///
+/// # Panics
+///
+/// Panics if [`try_compute_limit_rate`] returns an error other than
+/// [`RateError::NegativeWeight`], or if Newton's method does not converge.
 pub fn compute_limit_rate(beta: &[f64]) -> f64 {
-    if beta.is_empty() {
-        panic!("less than 1 beta is not allowed");
+    match try_compute_limit_rate(beta) {
+        Err(RateError::NegativeWeight { .. }) => RateSolver::new().solve_unvalidated(beta),
+        result => result,
     }
-    let beta_iter = beta
+    .unwrap_or_else(|error| panic!("{error}"))
+}
+
+/// Same as [`compute_limit_rate`], but returns an error instead of panicking
+/// or iterating forever
+///
+/// Use [`RateSolver`] to configure the solver.
+pub fn try_compute_limit_rate(beta: &[f64]) -> Result<f64, RateError> {
+    RateSolver::new().solve(beta).map(|solution| solution.rate)
+}
+
+/// The limit rate of every regime of a weight schedule, e.g. from
+/// [`Breakpoints::regimes`](crate::tscale_schedule::Breakpoints::regimes)
+///
+/// Takes (start tick, betas) pairs and returns (start tick, limit rate) pairs in the
+/// same order. Each rate is the growth the sequence approaches if the regime lasts
+/// long enough.
+pub fn regime_limit_rates<'a, B>(
+    regimes: impl IntoIterator<Item = (usize, &'a B)>,
+) -> Result<Vec<(usize, f64)>, RateError>
+where
+    B: AsRef<[f64]> + ?Sized + 'a,
+{
+    regimes
+        .into_iter()
+        .map(|(tick, beta)| try_compute_limit_rate(beta.as_ref()).map(|rate| (tick, rate)))
+        .collect()
+}
+
+/// Estimate the stochastic growth rate log λs when every tick draws new weights
+/// according to `noise`
+///
+/// log λs is the limit of log(at)/t, the Lyapunov exponent of the product of random
+/// companion matrices. It is smaller than the log of [`compute_limit_rate`] of the mean
+/// weights, so random environments slow down growth. Each of the `replicates` runs
+/// multiplies `ticks` random matrices into a state vector that is renormalized every
+/// tick, skipping the first tenth as burn-in.
+///
+/// Fails with [`RateError::TooFewReplicates`] for less than 2 replicates, which the
+/// standard error needs, and with [`RateError::TooFewTicks`] if no tick is left after
+/// the burn-in. [`Noise::Weights`] can draw every weight as 0, so a replicate can die
+/// out; such replicates are counted in [`StochasticGrowth::extinct`], and if less than
+/// 2 survive it fails with [`RateError::Extinct`].
+///
+/// The Tuljapurkar approximation log λ1 - τ²/(2λ1²) is reported for comparison, where
+/// τ² = Σ Cov(βi, βj)*∂λ/∂βi*∂λ/∂βj, i.e. Σ (σ*βi*∂λ/∂βi)² for
+/// [`Noise::Weights`] and (σ*Σ βi*∂λ/∂βi)² for [`Noise::Multiplicative`].
+pub fn stochastic_growth_rate(
+    beta: &[f64],
+    noise: Noise,
+    ticks: usize,
+    replicates: usize,
+    seed: u64,
+) -> Result<StochasticGrowth, RateError> {
+    let rate = try_compute_limit_rate(beta)?;
+    if replicates < 2 {
+        return Err(RateError::TooFewReplicates {
+            needed: 2,
+            found: replicates,
+        });
+    }
+    let burn_in = ticks / 10;
+    if ticks <= burn_in {
+        return Err(RateError::TooFewTicks {
+            needed: burn_in + 1,
+            found: ticks,
+        });
+    }
+    let order = beta.len();
+    let mut seeds = SplitMix64::new(seed);
+    let estimates = (0..replicates)
+        .filter_map(|_| {
+            let mut rng = SplitMix64::new(seeds.next_u64());
+            let mut state = vec![1.0 / order as f64; order];
+            let mut drawn = vec![0.0; order];
+            let mut head = 0;
+            let mut log_growth = 0.0;
+            for tick in 0..ticks {
+                noise.draw_weights(beta, &mut drawn, &mut rng);
+                tscale_jump::step_ring(&mut state, &drawn, &mut head);
+                let newest = (head + order - 1) % order;
+                state[newest] *= noise.draw_factor(&mut rng);
+                let sum = state.iter().sum::<f64>();
+                if sum == 0.0 {
+                    return None;
+                }
+                state.iter_mut().for_each(|value| *value /= sum);
+                if tick >= burn_in {
+                    log_growth += sum.ln();
+                }
+            }
+            Some(log_growth / (ticks - burn_in) as f64)
+        })
+        .collect::<Vec<_>>();
+    if estimates.len() < 2 {
+        return Err(RateError::Extinct {
+            survivors: estimates.len(),
+        });
+    }
+
+    let count = estimates.len() as f64;
+    let log_rate = estimates.iter().sum::<f64>() / count;
+    let variance = estimates
         .iter()
-        .enumerate()
-        .skip(1)
-        .map(|(i, beta)| (i as f64, *beta));
-    let fx = |x: f64| {
-        beta_iter
-            .clone()
-            .fold(beta[0] - x, |sum, (i, beta)| beta.mul_add(x.powf(-i), sum))
+        .map(|estimate| (estimate - log_rate).powi(2))
+        .sum::<f64>()
+        / (count - 1.0);
+    let standard_error = (variance / count).sqrt();
+
+    let sensitivities = rate_sensitivities(beta)?;
+    let tau = match noise {
+        Noise::Weights { std_dev } => sensitivities
+            .iter()
+            .zip(beta)
+            .map(|(sensitivity, beta)| (std_dev * beta * sensitivity.derivative).powi(2))
+            .sum::<f64>(),
+        Noise::Multiplicative { std_dev } => (std_dev
+            * sensitivities
+                .iter()
+                .zip(beta)
+                .map(|(sensitivity, beta)| beta * sensitivity.derivative)
+                .sum::<f64>())
+        .powi(2),
     };
-    let dfdx = |x: f64| {
-        beta_iter.clone().fold(-1.0, |sum, (i, beta)| {
-            (-beta * i).mul_add(x.powf(-i - 1.0), sum)
+    Ok(StochasticGrowth {
+        log_rate,
+        standard_error,
+        confidence_interval: (
+            1.96f64.mul_add(-standard_error, log_rate),
+            1.96f64.mul_add(standard_error, log_rate),
+        ),
+        tuljapurkar: rate.ln() - tau / (2.0 * rate * rate),
+        extinct: replicates - estimates.len(),
+    })
+}
+
+/// The sensitivity of [`compute_limit_rate`] to every beta
+///
+/// Differentiating Σ βi*r^{1-i} - r = 0 implicitly gives
+///
+/// ∂r/∂βi = r^{1-i} / (1 + Σ (j-1)*βj*r^{-j})
+///
+/// The elasticities sum to 1/T, where T is the mean generation time.
+///
+/// Fails if [`try_compute_limit_rate`] does.
+pub fn rate_sensitivities(beta: &[f64]) -> Result<Vec<Sensitivity>, RateError> {
+    let rate = try_compute_limit_rate(beta)?;
+    let denominator = beta.iter().enumerate().fold(1.0, |sum, (i, beta)| {
+        (i as f64 * beta).mul_add(rate.powi(-(i as i32) - 1), sum)
+    });
+    Ok(beta
+        .iter()
+        .enumerate()
+        .map(|(i, beta)| {
+            let derivative = rate.powi(-(i as i32)) / denominator;
+            Sensitivity {
+                derivative,
+                elasticity: beta / rate * derivative,
+            }
         })
+        .collect())
+}
+
+/// The stable distribution of the last C terms, indexed like beta, i.e. newest first
+///
+/// Index i is the share of a{t-1-i} in the sum of the last C terms once at/a{t-1}
+/// has settled at the limit rate r. It is the right eigenvector of the companion matrix,
+/// r^-i normalized to sum 1. `array` of [`TScale::new_with_config`] is oldest first, so
+/// use [`stable_window`] to start a sequence in equilibrium.
+///
+/// [`TScale::new_with_config`]: crate::tscale_sequence::TScale::new_with_config
+pub fn stable_distribution(beta: &[f64]) -> Result<Vec<f64>, RateError> {
+    let rate = try_compute_limit_rate(beta)?;
+    let distribution = (0..beta.len())
+        .map(|i| rate.powi(-(i as i32)))
+        .collect::<Vec<_>>();
+    let sum = distribution.iter().sum::<f64>();
+    Ok(distribution.into_iter().map(|share| share / sum).collect())
+}
+
+/// [`stable_distribution`] oldest first, the `array` of
+/// [`TScale::new_with_config`](crate::tscale_sequence::TScale::new_with_config) that
+/// grows by exactly the limit rate from the first step on
+pub fn stable_window(beta: &[f64]) -> Result<Vec<f64>, RateError> {
+    let mut window = stable_distribution(beta)?;
+    window.reverse();
+    Ok(window)
+}
+
+/// The reproductive value of each of the last C terms, indexed like beta
+///
+/// Index i is how much a{t-1-i} contributes to all future terms, relative to the
+/// others. It is the left eigenvector of the companion matrix, Σ_{k≥i} βk*r^{i-1-k},
+/// normalized so that its dot product with [`stable_distribution`] is 1. For any state,
+/// vi*a{t-1-i} / Σ vj*a{t-1-j} is the share of future growth that comes from index i.
+pub fn reproductive_values(beta: &[f64]) -> Result<Vec<f64>, RateError> {
+    let rate = try_compute_limit_rate(beta)?;
+    // v{i} = (βi + v{i+1}) / r, starting from the oldest term
+    let mut values = vec![0.0; beta.len()];
+    let mut next = 0.0;
+    for (value, beta) in values.iter_mut().zip(beta).rev() {
+        next = (beta + next) / rate;
+        *value = next;
+    }
+    let distribution = stable_distribution(beta)?;
+    let dot = values
+        .iter()
+        .zip(&distribution)
+        .map(|(value, share)| value * share)
+        .sum::<f64>();
+    Ok(values.into_iter().map(|value| value / dot).collect())
+}
+
+/// The factor s such that the limit rate of s*β is `target`
+///
+/// It is the inverse of [`compute_limit_rate`] along the direction of β:
+/// s = r / Σ βi*r^{1-i}. Every positive target is reachable.
+pub fn scale_for_rate(beta: &[f64], target: f64) -> Result<f64, RateError> {
+    validate_beta(beta)?;
+    validate_target(target)?;
+    let sum = beta
+        .iter()
+        .enumerate()
+        .fold(0.0, |sum, (i, beta)| beta.mul_add(target.powi(-(i as i32)), sum));
+    Ok(target / sum)
+}
+
+/// The value of the beta at `index` that makes the limit rate `target`,
+/// keeping the other betas unchanged
+///
+/// Returns [`RateError::Unreachable`] if the other betas alone already grow faster
+/// than `target`, i.e. the beta would have to be negative.
+pub fn adjust_for_rate(beta: &[f64], index: usize, target: f64) -> Result<f64, RateError> {
+    match validate_beta(beta) {
+        Ok(()) | Err(RateError::AllZero) => {}
+        Err(error) => return Err(error),
+    }
+    if index >= beta.len() {
+        return Err(RateError::IndexOutOfRange { index });
+    }
+    validate_target(target)?;
+    let others = beta
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != index)
+        .fold(0.0, |sum, (i, beta)| beta.mul_add(target.powi(-(i as i32)), sum));
+    let value = (target - others) * target.powi(index as i32);
+    if value < 0.0 {
+        return Err(RateError::Unreachable);
+    }
+    Ok(value)
+}
+
+/// The particular solution for the constant forcing term `forcing`, see [`Equilibrium`]
+///
+/// Every solution is the particular solution plus a solution of the homogeneous
+/// sequence, so if Σ βi < 1 the sequence settles at [`Equilibrium::Fixed`], and if
+/// Σ βi = 1 it ends up growing by [`Equilibrium::Drift`] every tick.
+pub fn constant_forcing_equilibrium(
+    beta: &[f64],
+    forcing: f64,
+) -> Result<Equilibrium, RateError> {
+    match validate_beta(beta) {
+        Ok(()) | Err(RateError::AllZero) => {}
+        Err(error) => return Err(error),
+    }
+    let sum = beta.iter().sum::<f64>();
+    if sum.approx(1.0) {
+        let mean_age = beta
+            .iter()
+            .enumerate()
+            .fold(0.0, |mean, (i, beta)| beta.mul_add((i + 1) as f64, mean));
+        Ok(Equilibrium::Drift(forcing / mean_age))
+    } else {
+        Ok(Equilibrium::Fixed(forcing / (1.0 - sum)))
+    }
+}
+
+fn validate_target(target: f64) -> Result<(), RateError> {
+    if target.is_finite() && target > 0.0 {
+        Ok(())
+    } else {
+        Err(RateError::InvalidTarget)
+    }
+}
+
+/// Check that the betas describe a valid t-scale sequence:
+/// at least one beta, all finite and non-negative, and at least one positive.
+pub fn validate_beta(beta: &[f64]) -> Result<(), RateError> {
+    if beta.is_empty() {
+        return Err(RateError::Empty);
+    }
+    if let Some(index) = beta.iter().position(|beta| !beta.is_finite()) {
+        return Err(RateError::NonFinite { index });
+    }
+    if let Some(index) = beta.iter().position(|beta| *beta < 0.0) {
+        return Err(RateError::NegativeWeight { index });
+    }
+    if beta.iter().all(|beta| *beta == 0.0) {
+        return Err(RateError::AllZero);
+    }
+    Ok(())
+}
+
+/// Find all roots of the characteristic polynomial
+///
+/// at = β1*a{t-1} + β2*a{t-2} + ... + βn*a{t-n}
+///
+/// has the general solution at = Σ cj*t^k*rj^t, where rj are the roots of
+/// x^n - β1*x^{n-1} - ... - βn. The roots are sorted by modulus in descending order,
+/// so for valid betas the first one is the limit rate from [`compute_limit_rate`] and
+/// the others describe the transient behavior, e.g. complex roots cause oscillations.
+///
+/// The roots are found with the Durand–Kerner method, roots that are closer than
+/// about 1e-4 are reported as a single root with a higher multiplicity.
+///
+/// # Panics
+///
+/// Panics if there is no beta or a beta is NaN or infinite.
+pub fn characteristic_roots(beta: &[f64]) -> Vec<CharacteristicRoot> {
+    validate_polynomial(beta).unwrap_or_else(|error| panic!("{error}"));
+    // trailing zero betas lower the degree and add roots at 0
+    let zeros = beta.iter().rev().take_while(|beta| **beta == 0.0).count();
+    let reduced = &beta[..beta.len() - zeros];
+    let mut values = durand_kerner(reduced);
+    values.resize(values.len() + zeros, Complex::ZERO);
+
+    let mut roots: Vec<CharacteristicRoot> = Vec::new();
+    let mut merged = vec![false; values.len()];
+    for i in 0..values.len() {
+        if merged[i] {
+            continue;
+        }
+        // a cluster grows transitively, the estimates of a multiple root scatter around it
+        merged[i] = true;
+        let mut cluster = vec![i];
+        let mut next = 0;
+        while next < cluster.len() {
+            let center = values[cluster[next]];
+            let tolerance = ROOT_MERGE_TOLERANCE * center.norm().max(1.0);
+            for j in i + 1..values.len() {
+                if !merged[j] && (values[j] - center).norm() <= tolerance {
+                    merged[j] = true;
+                    cluster.push(j);
+                }
+            }
+            next += 1;
+        }
+        let sum = cluster
+            .iter()
+            .fold(Complex::ZERO, |sum, j| sum + values[*j]);
+        let mut value = sum / Complex::from(cluster.len() as f64);
+        if value != Complex::ZERO {
+            value = polish_root(reduced, value, cluster.len());
+        }
+        if value.im.abs() <= 1e-10 * value.norm().max(1.0) {
+            value.im = 0.0;
+        }
+        roots.push(CharacteristicRoot {
+            value,
+            multiplicity: cluster.len(),
+        });
+    }
+    roots.sort_by(|a, b| {
+        b.value
+            .norm()
+            .partial_cmp(&a.value.norm())
+            .unwrap_or(Ordering::Equal)
+            .then(b.value.re.partial_cmp(&a.value.re).unwrap_or(Ordering::Equal))
+            .then(b.value.im.partial_cmp(&a.value.im).unwrap_or(Ordering::Equal))
+    });
+    roots
+}
+
+/// The period of the weights: the gcd of all i with βi > 0
+///
+/// If it is greater than 1 (e.g. `[0.0, 1.0]` or `[0.0, 0.0, 2.0]`), the sequence splits
+/// into interleaved subsequences that never mix, so at/a{t-1} oscillates forever instead
+/// of converging. [`ClosedForm::phase_ratios`](crate::tscale_closed_form::ClosedForm::phase_ratios)
+/// gives the ratios it oscillates between. Returns 0 if no beta is positive.
+pub fn period(beta: &[f64]) -> usize {
+    fn gcd(a: usize, b: usize) -> usize {
+        if b == 0 {
+            a
+        } else {
+            gcd(b, a % b)
+        }
+    }
+    beta.iter()
+        .enumerate()
+        .filter(|(_, beta)| **beta > 0.0)
+        .fold(0, |period, (i, _)| gcd(period, i + 1))
+}
+
+/// Whether at/a{t-1} converges, i.e. [`period`] is 1
+pub fn is_primitive(beta: &[f64]) -> bool {
+    period(beta) == 1
+}
+
+/// The ratio |r2|/|r1| between the two largest characteristic roots
+///
+/// The ratio at/a{t-1} approaches the limit rate roughly like (|r2|/|r1|)^t, so the
+/// smaller it is, the faster the sequence settles. It is 0 if every other root is 0,
+/// and 1 or more if the sequence never settles, e.g. for periodic weights.
+///
+/// Fails if there is no beta or a beta is NaN or infinite.
+pub fn damping_ratio(beta: &[f64]) -> Result<f64, RateError> {
+    validate_polynomial(beta)?;
+    Ok(root_ratio(&characteristic_roots(beta)))
+}
+
+/// [`damping_ratio`] of roots sorted like [`characteristic_roots`]
+fn root_ratio(roots: &[CharacteristicRoot]) -> f64 {
+    if roots[0].multiplicity > 1 {
+        return 1.0;
+    }
+    roots
+        .get(1)
+        .map_or(0.0, |second| second.value.norm() / roots[0].value.norm())
+}
+
+/// Estimate how many terms it takes until at/a{t-1} is within `tolerance`
+/// of [`compute_limit_rate`]
+///
+/// The estimate assumes the initial values excite the second root about as much as the
+/// dominant one, i.e. |c2/c1| ≈ 1 in the [closed form](crate::tscale_closed_form).
+/// Returns `None` if the ratio does not settle at all, if `tolerance` is not positive,
+/// or if [`damping_ratio`] fails.
+pub fn settle_steps(beta: &[f64], tolerance: f64) -> Option<usize> {
+    if tolerance.is_nan() || tolerance <= 0.0 || validate_polynomial(beta).is_err() {
+        return None;
+    }
+    let roots = characteristic_roots(beta);
+    let ratio = root_ratio(&roots);
+    if ratio >= 1.0 {
+        return None;
+    }
+    if ratio == 0.0 {
+        // only roots at 0 are left, they vanish after the initial values
+        return Some(beta.len());
+    }
+    // |at/a{t-1} - r1| ≈ |r1 - r2| * ratio^(t-1)
+    let distance = (roots[0].value - roots[1].value).norm();
+    let steps = (tolerance / distance).log(ratio) + 1.0;
+    Some(steps.ceil().max(0.0) as usize)
+}
+
+/// Check that the characteristic polynomial exists, any sign is allowed
+fn validate_polynomial(beta: &[f64]) -> Result<(), RateError> {
+    if beta.is_empty() {
+        return Err(RateError::Empty);
+    }
+    if let Some(index) = beta.iter().position(|beta| !beta.is_finite()) {
+        return Err(RateError::NonFinite { index });
+    }
+    Ok(())
+}
+
+/// All roots of x^n - β1*x^{n-1} - ... - βn, with repetition
+fn durand_kerner(beta: &[f64]) -> Vec<Complex> {
+    let degree = beta.len();
+    if degree == 0 {
+        return Vec::new();
+    }
+    let polynomial = |z: Complex| {
+        beta.iter()
+            .fold(Complex::ONE, |acc, beta| acc * z - Complex::from(*beta))
     };
-    let mut x = 1.;
-    loop {
-        let x1 = x - fx(x) / dfdx(x);
+    // every root lies inside this circle
+    let radius = 1.0 + beta.iter().fold(0.0_f64, |max, beta| max.max(beta.abs()));
+    let mut roots = (0..degree)
+        .map(|k| Complex::from_polar(radius, TAU * k as f64 / degree as f64 + 0.4))
+        .collect::<Vec<_>>();
+    for _ in 0..DURAND_KERNER_ITERATIONS {
+        let mut change = 0.0_f64;
+        for k in 0..degree {
+            let denominator = (0..degree)
+                .filter(|j| *j != k)
+                .fold(Complex::ONE, |acc, j| acc * (roots[k] - roots[j]));
+            let delta = polynomial(roots[k]) / denominator;
+            if delta.re.is_finite() && delta.im.is_finite() {
+                roots[k] = roots[k] - delta;
+                change = change.max(delta.norm() / roots[k].norm().max(1.0));
+            }
+        }
+        if change <= f64::EPSILON {
+            break;
+        }
+    }
+    roots
+}
 
-        if x1.approx(x) {
-            return x1;
+/// Refine a root of multiplicity m with Newton's method on the (m-1)-th derivative
+/// of x^n - β1*x^{n-1} - ... - βn, where it is a simple root.
+fn polish_root(beta: &[f64], root: Complex, multiplicity: usize) -> Complex {
+    let mut coefficients = std::iter::once(1.0)
+        .chain(beta.iter().map(|beta| -beta))
+        .collect::<Vec<_>>();
+    let derive = |coefficients: &[f64]| {
+        let degree = coefficients.len() - 1;
+        coefficients[..degree]
+            .iter()
+            .enumerate()
+            .map(|(i, c)| c * (degree - i) as f64)
+            .collect::<Vec<_>>()
+    };
+    for _ in 1..multiplicity {
+        coefficients = derive(&coefficients);
+    }
+    let derivative = derive(&coefficients);
+    let evaluate = |coefficients: &[f64], z: Complex| {
+        coefficients
+            .iter()
+            .fold(Complex::ZERO, |acc, c| acc * z + Complex::from(*c))
+    };
+    let mut z = root;
+    for _ in 0..20 {
+        let slope = evaluate(&derivative, z);
+        if slope == Complex::ZERO {
+            break;
+        }
+        let delta = evaluate(&coefficients, z) / slope;
+        if !(delta.re.is_finite() && delta.im.is_finite()) {
+            break;
+        }
+        z = z - delta;
+        if delta.norm() <= f64::EPSILON * z.norm() {
+            break;
         }
-        x = x1;
     }
+    z
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::tscale_sequence::compute_rate_with_data;
+    use crate::{
+        tscale_forcing::Constant,
+        tscale_sequence::{compute_rate_at, compute_rate_with_data, TScale},
+    };
 
     use super::*;
 
@@ -86,4 +749,224 @@ mod tests {
             test_rate(*array,*weight);
         }
     }
+
+    #[test]
+    fn test_sensitivities(){
+        let weight = [0.4, 1.2, 0.3, 0.1, 0.0];
+        let rate = compute_limit_rate(&weight);
+        let sensitivities = rate_sensitivities(&weight).unwrap();
+        for (i, sensitivity) in sensitivities.iter().enumerate() {
+            let mut nudged = weight;
+            nudged[i] += 1e-6;
+            let derivative = (compute_limit_rate(&nudged) - rate) / 1e-6;
+            (derivative - sensitivity.derivative).abs().assert_approx(0.0);
+        }
+        // the oldest group has no influence on births
+        assert_eq!(sensitivities[4].elasticity, 0.0);
+        assert!(sensitivities[1].elasticity > sensitivities[0].elasticity);
+        assert_eq!(rate_sensitivities(&[0.0, 0.0]), Err(RateError::AllZero));
+    }
+
+    #[test]
+    fn test_inverse(){
+        let weight = [0.4, 1.2, 0.3, 0.1, 0.0];
+        let scale = scale_for_rate(&weight, 1.02).unwrap();
+        compute_limit_rate(&weight.map(|beta| beta * scale)).assert_approx(1.02);
+
+        let mut adjusted = weight;
+        adjusted[1] = adjust_for_rate(&weight, 1, 1.02).unwrap();
+        compute_limit_rate(&adjusted).assert_approx(1.02);
+
+        assert_eq!(adjust_for_rate(&[1.0, 1.0], 0, 0.5), Err(RateError::Unreachable));
+        assert_eq!(scale_for_rate(&weight, 0.0), Err(RateError::InvalidTarget));
+        assert_eq!(
+            adjust_for_rate(&weight, 5, 1.0),
+            Err(RateError::IndexOutOfRange { index: 5 })
+        );
+    }
+
+    #[test]
+    fn test_eigenvectors(){
+        let array = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let weight = [0.4, 1.2, 0.3, 0.1, 0.05];
+        let rate = compute_limit_rate(&weight);
+        let stable = stable_distribution(&weight).unwrap();
+        let values = reproductive_values(&weight).unwrap();
+        stable.iter().sum::<f64>().assert_approx(1.0);
+        stable
+            .iter()
+            .zip(&values)
+            .map(|(share, value)| share * value)
+            .sum::<f64>()
+            .assert_approx(1.0);
+
+        // the last C terms settle at the stable distribution, newest first
+        let mut tscale = TScale::new_with_config(array, weight);
+        tscale.iter().nth(300);
+        let window = tscale.current_window();
+        let sum = window.iter().sum::<f64>();
+        for (term, share) in window.iter().rev().zip(&stable) {
+            (term / sum).assert_approx(*share);
+        }
+        let mut equilibrium =
+            TScale::new_with_config(stable_window(&weight).unwrap().try_into().unwrap(), weight);
+        (equilibrium.step() / stable[0]).assert_approx(rate);
+        (equilibrium.step() / equilibrium.peek_next()).assert_approx(1.0 / rate);
+
+        // the total reproductive value grows by exactly r every tick, from the start
+        let mut tscale = TScale::new_with_config(array, weight);
+        let total = |window: [f64; 5]| {
+            window.iter().rev().zip(&values).map(|(term, value)| term * value).sum::<f64>()
+        };
+        let before = total(tscale.current_window());
+        tscale.step();
+        (total(tscale.current_window()) / before).assert_approx(rate);
+    }
+
+    #[test]
+    fn test_errors(){
+        assert_eq!(try_compute_limit_rate(&[]), Err(RateError::Empty));
+        assert_eq!(try_compute_limit_normal_rate(0), Err(RateError::Empty));
+        assert_eq!(
+            try_compute_limit_rate(&[1.0, -0.5]),
+            Err(RateError::NegativeWeight { index: 1 })
+        );
+        assert_eq!(try_compute_limit_rate(&[0.0, 0.0]), Err(RateError::AllZero));
+        assert_eq!(
+            try_compute_limit_rate(&[f64::NAN, 1.0]),
+            Err(RateError::NonFinite { index: 0 })
+        );
+        try_compute_limit_rate(&[1.0, 1.0]).unwrap().assert_approx(1.618034);
+        try_compute_limit_normal_rate(2).unwrap().assert_approx(1.618034);
+
+        // the panicking version still accepts mixed signs
+        test_rate([1.0, 1.0], [1.2, -0.1]);
+        let dominant = characteristic_roots(&[1.2, -0.1])[0].value;
+        compute_limit_rate(&[1.2, -0.1]).assert_approx(dominant.re);
+    }
+
+    #[test]
+    fn test_settle_steps(){
+        // Fibonacci sequence
+        let weight = [1.0, 1.0];
+        damping_ratio(&weight).unwrap().assert_approx(0.381966);
+
+        let tolerance = 1e-6;
+        let steps = settle_steps(&weight, tolerance).unwrap();
+        let rate = compute_limit_rate(&weight);
+        let error = |t| (compute_rate_at(t, [0.0, 1.0], weight) - rate).abs();
+        assert!(error(steps) <= tolerance);
+        assert!(error(steps - 2) > tolerance);
+
+        assert_eq!(settle_steps(&[0.0, 1.0], tolerance), None);
+        assert_eq!(settle_steps(&weight, 0.0), None);
+        assert_eq!(settle_steps(&weight, f64::NAN), None);
+        assert_eq!(settle_steps(&[], tolerance), None);
+        assert_eq!(settle_steps(&[f64::INFINITY, 1.0], tolerance), None);
+        assert_eq!(damping_ratio(&[]), Err(RateError::Empty));
+        assert_eq!(damping_ratio(&[1.0, f64::NAN]), Err(RateError::NonFinite { index: 1 }));
+    }
+
+    #[test]
+    fn test_equilibrium(){
+        let Ok(Equilibrium::Fixed(fixed)) = constant_forcing_equilibrium(&[0.5, 0.3], 30.0) else {
+            panic!("β sums to 0.8")
+        };
+        fixed.assert_approx(150.0);
+        assert_eq!(
+            constant_forcing_equilibrium(&[0.0, 0.0], 30.0),
+            Ok(Equilibrium::Fixed(30.0))
+        );
+        assert_eq!(constant_forcing_equilibrium(&[], 30.0), Err(RateError::Empty));
+
+        // at = 0.5*a{t-1} + 0.5*a{t-2} + 3 gains 3 / 1.5 per tick in the long run
+        let Ok(Equilibrium::Drift(drift)) = constant_forcing_equilibrium(&[0.5, 0.5], 3.0) else {
+            panic!("β sums to 1")
+        };
+        drift.assert_approx(2.0);
+        let mut tscale =
+            TScale::new_with_config([0.0, 0.0], [0.5, 0.5]).with_forcing(Constant(3.0));
+        tscale.iter().nth(100);
+        let older = tscale.step();
+        (tscale.step() - older).assert_approx(drift);
+    }
+
+    #[test]
+    fn test_stochastic_growth(){
+        let weight = [0.4, 1.2, 0.3];
+        let rate = compute_limit_rate(&weight);
+        let growth =
+            stochastic_growth_rate(&weight, Noise::Weights { std_dev: 0.0 }, 200, 2, 1).unwrap();
+        growth.log_rate.assert_approx(rate.ln());
+        growth.tuljapurkar.assert_approx(rate.ln());
+
+        for noise in [Noise::Weights { std_dev: 0.2 }, Noise::Multiplicative { std_dev: 0.2 }] {
+            let growth = stochastic_growth_rate(&weight, noise, 2000, 50, 7).unwrap();
+            let (low, high) = growth.confidence_interval;
+            assert!(low < growth.log_rate && growth.log_rate < high);
+            assert!(growth.log_rate < rate.ln(), "{noise:?}: {growth:?}");
+            assert!((growth.log_rate - growth.tuljapurkar).abs() < 1e-3, "{noise:?}: {growth:?}");
+        }
+
+        let noise = Noise::Weights { std_dev: 0.2 };
+        assert_eq!(
+            stochastic_growth_rate(&weight, noise, 200, 1, 1),
+            Err(RateError::TooFewReplicates { needed: 2, found: 1 })
+        );
+        assert_eq!(
+            stochastic_growth_rate(&weight, noise, 0, 2, 1),
+            Err(RateError::TooFewTicks { needed: 1, found: 0 })
+        );
+
+        // sparse weights die out once every drawn weight is 0
+        let noise = Noise::Weights { std_dev: 0.5 };
+        let growth = stochastic_growth_rate(&[0.0, 0.0, 2.0], noise, 200, 20, 3).unwrap();
+        assert!(growth.extinct > 0 && growth.extinct < 20, "{growth:?}");
+        assert!(growth.log_rate.is_finite() && growth.standard_error.is_finite());
+        assert_eq!(
+            stochastic_growth_rate(&[1.1], Noise::Weights { std_dev: 5.0 }, 2000, 20, 3),
+            Err(RateError::Extinct { survivors: 0 })
+        );
+    }
+
+    #[test]
+    fn test_period(){
+        assert_eq!(period(&[1.0, 1.0]), 1);
+        assert_eq!(period(&[0.0, 1.0]), 2);
+        assert_eq!(period(&[0.0, 0.0, 2.0]), 3);
+        assert_eq!(period(&[0.0, 1.0, 0.0, 0.5]), 2);
+        assert_eq!(period(&[0.0, 0.0]), 0);
+        assert!(is_primitive(&[0.4, 1.2, 0.3, 0.1, 0.0]));
+    }
+
+    #[test]
+    fn test_characteristic_roots(){
+        // Fibonacci sequence
+        let roots = characteristic_roots(&[1.0, 1.0]);
+        assert_eq!(roots.len(), 2);
+        roots[0].value.re.assert_approx(1.618034);
+        roots[1].value.re.assert_approx(-0.618034);
+
+        let weight = [0.4, 1.2, 0.3, 0.1, 0.0];
+        let roots = characteristic_roots(&weight);
+        roots[0].value.re.assert_approx(compute_limit_rate(&weight));
+        assert_eq!(roots[4].value, Complex::ZERO);
+
+        // x^3 - 3x^2 + 3x - 1 = (x - 1)^3
+        let roots = characteristic_roots(&[3.0, -3.0, 1.0]);
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].multiplicity, 3);
+        roots[0].value.re.assert_approx(1.0);
+
+        // cube roots of unity, all with modulus 1
+        let roots = characteristic_roots(&[0.0, 0.0, 1.0]);
+        assert_eq!(roots.len(), 3);
+        roots.iter().for_each(|root| root.value.norm().assert_approx(1.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "beta 0 is not finite")]
+    fn test_non_finite_roots(){
+        characteristic_roots(&[f64::NAN, 1.0]);
+    }
 }
@@ -0,0 +1,234 @@
+//! T-scale sequences whose weights change over time.
+//!
+//! A schedule decides the weights of every step, e.g. a plague era followed by a baby
+//! boom:
+//!
+//! ```rust
+//! # use tscale_sequence::{tscale_schedule::Breakpoints, tscale_sequence::TScale};
+//! let schedule = Breakpoints::new(vec![(100, [0.2, 0.3]), (150, [0.8, 0.6])]);
+//! let mut tscale =
+//!     TScale::new_with_config([100.0, 100.0], [0.5, 0.5]).with_schedule(schedule);
+//! let value = tscale.iter().nth(200).unwrap();
+//! ```
+//!
+//! [`regime_limit_rates`](crate::tscale_rate::regime_limit_rates) gives the limit rate
+//! of every regime.
+
+use std::ops::{Add, Mul};
+
+use crate::{
+    tscale_jump,
+    tscale_sequence::{Bounded, RingBuffer, TScale},
+};
+
+/// A source of the weights of every step
+pub trait Schedule<T, const C: usize> {
+    /// the weights from `tick` on, or `None` to keep the current weights
+    ///
+    /// `tick` counts the steps starting from 0 and is called in ascending order.
+    fn weights(&mut self, tick: usize) -> Option<[T; C]>;
+}
+
+impl<T, F, const C: usize> Schedule<T, C> for F
+where
+    F: FnMut(usize) -> [T; C],
+{
+    fn weights(&mut self, tick: usize) -> Option<[T; C]> {
+        Some(self(tick))
+    }
+}
+
+/// A list of (tick, weights) pairs, the weights apply from their tick until the next one
+///
+/// Before the first breakpoint the weights of the [`TScale`] are used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoints<T, const C: usize> {
+    breakpoints: Vec<(usize, [T; C])>,
+    /// index of the next breakpoint to apply
+    next: usize,
+}
+
+impl<T, const C: usize> Breakpoints<T, C> {
+    /// create from (tick, weights) pairs in any order
+    ///
+    /// If a tick appears more than once, the last pair wins.
+    pub fn new(breakpoints: impl Into<Vec<(usize, [T; C])>>) -> Self {
+        let mut breakpoints = breakpoints.into();
+        breakpoints.sort_by_key(|(tick, _)| *tick);
+        Self {
+            breakpoints,
+            next: 0,
+        }
+    }
+
+    /// the (tick, weights) pairs in ascending order of tick
+    pub fn regimes(&self) -> impl Iterator<Item = (usize, &[T; C])> {
+        self.breakpoints.iter().map(|(tick, weight)| (*tick, weight))
+    }
+}
+
+impl<T: Clone, const C: usize> Schedule<T, C> for Breakpoints<T, C> {
+    fn weights(&mut self, tick: usize) -> Option<[T; C]> {
+        let start = self.next;
+        while self
+            .breakpoints
+            .get(self.next)
+            .is_some_and(|(start, _)| *start <= tick)
+        {
+            self.next += 1;
+        }
+        (self.next > start).then(|| self.breakpoints[self.next - 1].1.clone())
+    }
+}
+
+impl<T, const C: usize> TScale<T, C> {
+    /// take the weights of every step from `schedule` from now on
+    pub const fn with_schedule<S: Schedule<T, C>>(
+        self,
+        schedule: S,
+    ) -> ScheduledTScale<T, C, S> {
+        ScheduledTScale {
+            tscale: self,
+            schedule,
+            tick: 0,
+        }
+    }
+}
+
+/// A [`TScale`] with time-varying weights, created by [`TScale::with_schedule`]
+pub struct ScheduledTScale<T, const C: usize, S> {
+    tscale: TScale<T, C>,
+    schedule: S,
+    tick: usize,
+}
+
+impl<T, const C: usize, S> ScheduledTScale<T, C, S> {
+    /// the number of steps taken so far, i.e. the tick of the next step
+    pub const fn tick(&self) -> usize {
+        self.tick
+    }
+
+    /// the weights of the last step
+    pub const fn current_weights(&self) -> &[T; C] {
+        &self.tscale.weight
+    }
+
+    /// the sequence with the weights of the last step
+    pub fn into_inner(self) -> TScale<T, C> {
+        self.tscale
+    }
+
+    /// into iterator
+    ///
+    /// It generates 10000 values by default, use [`Bounded::with_len`] or
+    /// [`Bounded::unbounded`] to change that.
+    pub fn iter(&mut self) -> Bounded<ScheduledTScaleIter<'_, T, C, S>> {
+        Bounded::new(ScheduledTScaleIter { tscale: self })
+    }
+}
+
+impl<T: Clone, const C: usize, S> ScheduledTScale<T, C, S> {
+    /// the last C values, oldest first
+    pub fn current_window(&self) -> [T; C] {
+        self.tscale.current_window()
+    }
+}
+
+impl<T, const C: usize, S> ScheduledTScale<T, C, S>
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+    S: Schedule<T, C>,
+{
+    /// advance one tick and return the new value
+    pub fn step(&mut self) -> T {
+        self.step_ring();
+        self.tscale.array[(self.tscale.head + C - 1) % C].clone()
+    }
+
+    /// advance one tick and return the oldest value
+    fn step_ring(&mut self) -> T {
+        if let Some(weight) = self.schedule.weights(self.tick) {
+            self.tscale.weight = weight;
+        }
+        self.tick += 1;
+        let TScale {
+            array,
+            weight,
+            head,
+        } = &mut self.tscale;
+        tscale_jump::step_ring(array, weight, head)
+    }
+}
+
+/// Recursive iterator with time-varying weights
+pub struct ScheduledTScaleIter<'a, T, const C: usize, S> {
+    tscale: &'a mut ScheduledTScale<T, C, S>,
+}
+
+impl<'a, T, const C: usize, S> RingBuffer for ScheduledTScaleIter<'a, T, C, S>
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+    S: Schedule<T, C>,
+{
+    type Value = T;
+
+    fn step(&mut self) -> T {
+        self.tscale.step_ring()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approximately::ApproxEq;
+
+    use super::*;
+    use crate::tscale_rate::{compute_limit_rate, regime_limit_rates};
+
+    #[test]
+    fn test_breakpoints() {
+        let array = [1.0, 2.0];
+        let (before, plague, boom) = ([0.5, 0.5], [0.2, 0.1], [1.0, 1.0]);
+        let schedule = Breakpoints::new(vec![(8, boom), (3, plague)]);
+        let mut scheduled = TScale::new_with_config(array, before).with_schedule(schedule);
+
+        let mut expected = TScale::new_with_config(array, before);
+        for tick in 0..12 {
+            if tick == 3 {
+                expected = TScale::new_with_config(expected.current_window(), plague);
+            } else if tick == 8 {
+                expected = TScale::new_with_config(expected.current_window(), boom);
+            }
+            scheduled.step().assert_approx(expected.step());
+        }
+        assert_eq!(scheduled.current_weights(), &boom);
+        assert_eq!(scheduled.tick(), 12);
+
+        let schedule = Breakpoints::new(vec![(100, [0.2, 0.3]), (0, [1.0, 1.0])]);
+        let rates = regime_limit_rates(schedule.regimes()).unwrap();
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].0, 0);
+        rates[0].1.assert_approx(1.618034);
+        assert_eq!(rates[1].0, 100);
+        rates[1].1.assert_approx(compute_limit_rate(&[0.2, 0.3]));
+    }
+
+    #[test]
+    fn test_closure() {
+        // alternate between two regimes every tick
+        let mut scheduled = TScale::new_with_config([1.0, 1.0], [1.0, 1.0])
+            .with_schedule(|tick: usize| [[1.0, 0.0], [0.0, 2.0]][tick % 2]);
+        let values = scheduled.iter().take(4).collect::<Vec<_>>();
+        assert_eq!(values, [1.0, 1.0, 1.0, 2.0]);
+        assert_eq!(scheduled.current_window(), [2.0, 4.0]);
+
+        // scheduled simulations can run past the default 10000 values
+        let mut scheduled = TScale::new_with_config([1.0, 1.0], [0.5, 0.5])
+            .with_schedule(Breakpoints::new(vec![(15_000, [0.25, 0.25])]));
+        assert_eq!(scheduled.iter().len(), 10_000);
+        assert_eq!(scheduled.iter().unbounded().take(20_000).count(), 20_000);
+        assert_eq!(scheduled.current_weights(), &[0.25, 0.25]);
+        let mut iter = scheduled.iter().with_len(1);
+        assert!(iter.nth(1).is_none() && iter.next().is_none());
+        assert_eq!(scheduled.tick(), 20_001);
+    }
+}
@@ -1,15 +1,24 @@
 //! Recursiver is a library for computing recursive sequences.
 use std::{
-    fmt::Debug,
+    error::Error,
+    fmt::{self, Debug, Display},
+    iter::FusedIterator,
     mem::ManuallyDrop,
-    ops::{Add, Div, Mul},
+    ops::{Add, Div, Mul, Sub},
 };
 
-const DEFAULT_GEN_LEN: usize = 10000;
+use crate::{
+    tscale_closed_form::ClosedForm,
+    tscale_jump::{self, JumpStrategy},
+};
+
+pub(crate) const DEFAULT_GEN_LEN: usize = 10000;
 
 pub struct TScale<T, const C: usize> {
-    array: [T; C],
-    weight: [T; C],
+    pub(crate) array: [T; C],
+    pub(crate) weight: [T; C],
+    /// `array` is a ring buffer, the oldest value is at `head`
+    pub(crate) head: usize,
 }
 
 impl<const C: usize> Default for TScale<f64, C> {
@@ -18,6 +27,7 @@ impl<const C: usize> Default for TScale<f64, C> {
         Self {
             array: [1.0; C],
             weight: [1.0; C],
+            head: 0,
         }
     }
 }
@@ -39,54 +49,183 @@ where
     pub const fn new_with_config(array: [T; C], weight: [T; C]) -> Self {
         Self {
             array,
-            weight
+            weight,
+            head: 0,
         }
     }
 
     /// into iterator
-    pub fn iter(&mut self) -> TScaleIter<'_, T, C> {
+    ///
+    /// It generates 10000 values by default, use [`Bounded::with_len`] or
+    /// [`Bounded::unbounded`] to change that.
+    pub fn iter(&mut self) -> Bounded<TScaleIter<'_, T, C>> {
         let Self {
             array,
             weight,
+            head,
         } = self;
 
-        TScaleIter {
+        Bounded::new(TScaleIter {
             array,
             weight,
-            gen_len: DEFAULT_GEN_LEN,
+            head,
+        })
+    }
+}
+
+impl<T, const C: usize> TScale<T, C>
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
+{
+    /// compute the n-th term without stepping through the sequence
+    ///
+    /// Term 0 is the first value the iterators yield. It runs in O(C³ log n),
+    /// see [`tscale_jump`] for details.
+    pub fn nth_term(&self, n: usize) -> T {
+        tscale_jump::nth_term(&self.current_window(), &self.weight, n)
+    }
+
+    /// compute the n-th term with the given jump strategy
+    pub fn nth_term_with(&self, n: usize, strategy: JumpStrategy) -> T {
+        tscale_jump::nth_term_with(&self.current_window(), &self.weight, n, strategy)
+    }
+
+    /// advance one tick and return the new value
+    ///
+    /// Unlike the iterators it never stops, and the returned value is the newest one,
+    /// i.e. the last value of [`current_window`](Self::current_window).
+    pub fn step(&mut self) -> T {
+        tscale_jump::step_ring(&mut self.array, &self.weight, &mut self.head);
+        self.array[(self.head + C - 1) % C].clone()
+    }
+
+    /// the value the next [`step`](Self::step) returns, without advancing
+    pub fn peek_next(&self) -> T {
+        (0..C)
+            .map(|index| &self.array[(self.head + index) % C])
+            .zip(self.weight.iter().rev())
+            .fold(T::default(), |acc, (a, b)| acc + a.clone() * b.clone())
+    }
+}
+
+impl<T, const C: usize> TScale<T, C>
+where
+    T: Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Div<Output = T>,
+    T: Clone + Default + PartialEq,
+{
+    /// step one tick backward and return the reconstructed value, which becomes the
+    /// oldest value of [`current_window`](Self::current_window)
+    ///
+    /// The recurrence can only be inverted if the last weight βC is not 0. Going
+    /// backward amplifies rounding errors by roughly the inverse of the smallest
+    /// characteristic root per tick, and nothing keeps the values non-negative.
+    pub fn step_back(&mut self) -> Result<T, RewindError> {
+        let last_weight = self.weight[C - 1].clone();
+        if last_weight == T::default() {
+            return Err(RewindError::ZeroLastWeight);
+        }
+        // newest = βC*oldest' + Σ βi*a{t-i}, solve it for the value before the oldest
+        let newest = (self.head + C - 1) % C;
+        let rest = (0..C - 1)
+            .map(|index| &self.array[(self.head + index) % C])
+            .zip(self.weight[..C - 1].iter().rev())
+            .fold(T::default(), |acc, (a, b)| acc + a.clone() * b.clone());
+        let value = (self.array[newest].clone() - rest) / last_weight;
+        self.array[newest] = value.clone();
+        self.head = newest;
+        Ok(value)
+    }
+
+    /// step `ticks` ticks backward, see [`step_back`](Self::step_back)
+    ///
+    /// On error the sequence is left unchanged.
+    pub fn rewind(&mut self, ticks: usize) -> Result<(), RewindError> {
+        for _ in 0..ticks {
+            self.step_back()?;
+        }
+        Ok(())
+    }
+}
+
+/// Why a [`TScale`] cannot step backward
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RewindError {
+    /// the last weight is 0, so the oldest value has no influence and is lost
+    ZeroLastWeight,
+}
+
+impl Display for RewindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroLastWeight => {
+                write!(f, "the last weight is 0, the sequence cannot be rewound")
+            }
+        }
+    }
+}
+
+impl Error for RewindError {}
+
+impl<T: Clone, const C: usize> TScale<T, C> {
+    /// the last C values, oldest first
+    pub fn current_window(&self) -> [T; C] {
+        std::array::from_fn(|index| self.array[(self.head + index) % C].clone())
+    }
+
+    /// save the current values, the weights are not part of the snapshot
+    pub fn snapshot(&self) -> TScaleSnapshot<T, C> {
+        TScaleSnapshot {
+            window: self.current_window(),
         }
     }
+
+    /// go back to the values of `snapshot`
+    pub fn restore(&mut self, snapshot: &TScaleSnapshot<T, C>) {
+        self.array.clone_from(&snapshot.window);
+        self.head = 0;
+    }
+}
+
+/// The values of a [`TScale`] at one tick, see [`TScale::snapshot`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TScaleSnapshot<T, const C: usize> {
+    window: [T; C],
+}
+
+impl<T, const C: usize> TScaleSnapshot<T, C> {
+    /// the saved values, oldest first
+    pub const fn window(&self) -> &[T; C] {
+        &self.window
+    }
 }
 
-/// Recursive iterator
+impl<const C: usize> TScale<f64, C> {
+    /// solve the closed-form expression of the sequence, see [`ClosedForm`]
+    pub fn closed_form(&self) -> ClosedForm {
+        ClosedForm::new(&self.current_window(), &self.weight)
+    }
+}
+
+/// Recursive iterator over a borrowed [`TScale`], see [`TScale::iter`]
 pub struct TScaleIter<'a, T, const C: usize> {
     array: &'a mut [T; C],
-    weight: &'a mut [T; C],
-    gen_len: usize,
+    weight: &'a [T; C],
+    head: &'a mut usize,
 }
 
-impl<'a, T, const C: usize> Iterator for TScaleIter<'a, T, C>
+impl<'a, T, const C: usize> RingBuffer for TScaleIter<'a, T, C>
 where
-    T: Add<Output = T> + Mul<Output = T> + Default + Clone,
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
 {
-    type Item = T;
+    type Value = T;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.gen_len > 0 {
-            self.gen_len -= 1;
+    fn step(&mut self) -> T {
+        tscale_jump::step_ring(&mut self.array[..], &self.weight[..], self.head)
+    }
 
-            let last_value = self
-                .array
-                .iter()
-                .zip(self.weight.iter().rev())
-                .fold(T::default(), |acc, (a, b)| acc + a.clone() * b.clone());
-            let first_value = self.array[0].clone();
-            (0..C - 1).for_each(|index| self.array[index] = self.array[index + 1].clone());
-            self.array[C - 1] = last_value;
-            Some(first_value)
-        } else {
-            None
-        }
+    fn skip(&mut self, n: usize) {
+        tscale_jump::skip_ring(&mut self.array[..], &self.weight[..], self.head, n);
     }
 }
 
@@ -95,25 +234,27 @@ where
     T: Add<Output = T> + Mul<Output = T> + Clone + Default,
 {
     type Item = T;
-    type IntoIter = TScaleIter<'a, T, C>;
+    type IntoIter = Bounded<TScaleIter<'a, T, C>>;
 
     fn into_iter(self) -> Self::IntoIter {
         let TScale {
             array,
             weight,
+            head,
         } = self;
-        TScaleIter {
+        Bounded::new(TScaleIter {
             array,
             weight,
-            gen_len: DEFAULT_GEN_LEN,
-        }
+            head,
+        })
     }
 }
 
+/// Recursive iterator over an owned [`TScale`]
 pub struct TScaleIntoIter<T, const C: usize> {
     array: ManuallyDrop<[T; C]>,
     weight: ManuallyDrop<[T; C]>,
-    gen_len: usize,
+    head: usize,
 }
 impl<T, const C: usize> Drop for TScaleIntoIter<T, C> {
     fn drop(&mut self) {
@@ -124,26 +265,18 @@ impl<T, const C: usize> Drop for TScaleIntoIter<T, C> {
     }
 }
 
-impl<T, const C: usize> Iterator for TScaleIntoIter<T, C>
+impl<T, const C: usize> RingBuffer for TScaleIntoIter<T, C>
 where
-    T: Add<Output = T> + Mul<Output = T> + Clone + Default + Debug,
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default,
 {
-    type Item = T;
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.gen_len > 0 {
-            self.gen_len -= 1;
-            let last_value = self
-                .array
-                .iter()
-                .zip(self.weight.iter().rev())
-                .fold(T::default(), |acc, (a, b)| acc + a.clone() * b.clone());
-            let first_value = self.array[0].clone();
-            (0..C - 1).for_each(|index| self.array[index] = self.array[index + 1].clone());
-            self.array[C - 1] = last_value;
-            Some(first_value)
-        } else {
-            None
-        }
+    type Value = T;
+
+    fn step(&mut self) -> T {
+        tscale_jump::step_ring(&mut self.array[..], &self.weight[..], &mut self.head)
+    }
+
+    fn skip(&mut self, n: usize) {
+        tscale_jump::skip_ring(&mut self.array[..], &self.weight[..], &mut self.head, n);
     }
 }
 
@@ -152,21 +285,121 @@ where
     T: Add<Output = T> + Mul<Output = T> + Clone + Default + Debug,
 {
     type Item = T;
-    type IntoIter = TScaleIntoIter<T, C>;
+    type IntoIter = Bounded<TScaleIntoIter<T, C>>;
 
     fn into_iter(self) -> Self::IntoIter {
         let Self {
             array,
             weight,
+            head,
         } = self;
-        TScaleIntoIter {
+        Bounded::new(TScaleIntoIter {
             array: ManuallyDrop::new(array),
             weight: ManuallyDrop::new(weight),
-            gen_len:DEFAULT_GEN_LEN,
+            head,
+        })
+    }
+}
+
+/// The state an iterator steps through
+pub(crate) trait RingBuffer {
+    type Value;
+
+    /// advance one tick and return the oldest value
+    fn step(&mut self) -> Self::Value;
+
+    /// advance `n` ticks
+    fn skip(&mut self, n: usize) {
+        for _ in 0..n {
+            self.step();
         }
     }
 }
 
+/// An iterator that stops after `gen_len` values, 10000 by default
+pub struct Bounded<I> {
+    iter: I,
+    gen_len: usize,
+}
+
+impl<I> Bounded<I> {
+    pub(crate) const fn new(iter: I) -> Self {
+        Self {
+            iter,
+            gen_len: DEFAULT_GEN_LEN,
+        }
+    }
+
+    /// generate `gen_len` values instead of 10000
+    pub const fn with_len(mut self, gen_len: usize) -> Self {
+        self.gen_len = gen_len;
+        self
+    }
+
+    /// never stop generating values
+    pub fn unbounded(self) -> Unbounded<I> {
+        Unbounded(self.iter)
+    }
+}
+
+impl<I, T> Iterator for Bounded<I>
+where
+    I: RingBuffer<Value = T>,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.gen_len == 0 {
+            return None;
+        }
+        self.gen_len -= 1;
+        Some(self.iter.step())
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if n >= self.gen_len {
+            self.iter.skip(self.gen_len);
+            self.gen_len = 0;
+            return None;
+        }
+        self.iter.skip(n);
+        self.gen_len -= n;
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.gen_len, Some(self.gen_len))
+    }
+}
+
+impl<I: RingBuffer> ExactSizeIterator for Bounded<I> {}
+
+impl<I: RingBuffer> FusedIterator for Bounded<I> {}
+
+/// An iterator that never ends, created by [`Bounded::unbounded`]
+pub struct Unbounded<I>(I);
+
+impl<I, T> Iterator for Unbounded<I>
+where
+    I: RingBuffer<Value = T>,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.0.step())
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.0.skip(n);
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<I: RingBuffer> FusedIterator for Unbounded<I> {}
 
 /// compute rate with an+1 and an
 pub fn compute_rate_with_data<T, const C: usize>(
@@ -187,6 +420,31 @@ where
     })
 }
 
+/// compute the rate of at and a{t-1} for a single, possibly far-off t
+///
+/// This is the t-th value of [`compute_rate_with_data`], but it is computed with
+/// [`JumpStrategy::Kitamasa`] instead of generating every term before t.
+pub fn compute_rate_at<T, const C: usize>(t: usize, array: [T; C], weight: [T; C]) -> T
+where
+    T: Add<Output = T> + Mul<Output = T> + Clone + Default + Div<Output = T>,
+{
+    assert!(C > 0, "C must be greater than 0");
+    if t == 0 {
+        return T::default();
+    }
+    if t < C {
+        return array[t].clone() / array[t - 1].clone();
+    }
+    // jump once until the window ends at term t, or at term t-1 if it only holds one term
+    let mut state = array;
+    tscale_jump::advance_with(&mut state, &weight, t + 1 - C.max(2), JumpStrategy::Kitamasa);
+    if let [.., previous, last] = &state[..] {
+        return last.clone() / previous.clone();
+    }
+    let previous = state[0].clone();
+    tscale_jump::step_ring(&mut state, &weight, &mut 0);
+    state[0].clone() / previous
+}
 
 #[cfg(test)]
 mod tests {
@@ -202,4 +460,159 @@ mod tests {
         // Fibonacci sequence
         compute_rate_with_data(50,array,weight).last().unwrap().assert_approx(1.618034);
     }
+
+    #[test]
+    fn test_nth() {
+        let array = [1.0, 1.0, 1.0];
+        let weight = [0.4, 1.2, 0.3];
+        let mut tscale = TScale::new_with_config(array, weight);
+        let stepped = tscale.iter().take(200).collect::<Vec<_>>();
+
+        let tscale = TScale::new_with_config(array, weight);
+        tscale.nth_term(150).assert_approx(stepped[150]);
+
+        let mut iter = tscale.into_iter();
+        iter.nth(99).unwrap().assert_approx(stepped[99]);
+        iter.next().unwrap().assert_approx(stepped[100]);
+        iter.nth(37).unwrap().assert_approx(stepped[138]);
+        assert!(iter.nth(10_000).is_none());
+    }
+
+    #[test]
+    fn test_gen_len() {
+        let mut tscale = TScale::new_with_config([1.0, 1.0], [0.5, 0.5]);
+        assert_eq!(tscale.iter().len(), 10000);
+        assert_eq!(tscale.iter().with_len(25_000).count(), 25_000);
+
+        let mut iter = tscale.iter().with_len(3);
+        assert_eq!(iter.size_hint(), (3, Some(3)));
+        iter.by_ref().for_each(drop);
+        assert!(iter.next().is_none());
+
+        let mut iter = tscale.iter().unbounded();
+        assert_eq!(iter.size_hint(), (usize::MAX, None));
+        assert_eq!(iter.by_ref().take(20_000).count(), 20_000);
+        iter.nth(1_000_000).unwrap().assert_approx(1.0);
+
+        let iter = TScale::new_with_config([1.0, 1.0], [0.5, 0.5])
+            .into_iter()
+            .with_len(12_345);
+        assert_eq!(iter.len(), 12_345);
+        assert_eq!(iter.count(), 12_345);
+    }
+
+    #[test]
+    fn test_skip() {
+        // short skips are stepped, long ones jump, both must agree with stepping
+        let array: [f64; 64] = std::array::from_fn(|i| (i % 7) as f64);
+        let weight: [f64; 64] = std::array::from_fn(|i| if i % 3 == 0 { 0.02 } else { 0.01 });
+        let stepped = TScale::new_with_config(array, weight)
+            .into_iter()
+            .take(2001)
+            .collect::<Vec<_>>();
+        for n in [0, 5, 63, 64, 400, 1999] {
+            let mut tscale = TScale::new_with_config(array, weight);
+            tscale.iter().next();
+            tscale.iter().nth(n).unwrap().assert_approx(stepped[n + 1]);
+        }
+        let mut iter = TScale::new_with_config(array, weight).into_iter().skip(1500);
+        iter.next().unwrap().assert_approx(stepped[1500]);
+    }
+
+    #[test]
+    fn test_ring_buffer() {
+        let array = [1.0, 2.0, 3.0, 4.0];
+        let weight = [0.4, 1.2, 0.3, 0.1];
+        let stepped = TScale::new_with_config(array, weight)
+            .into_iter()
+            .take(20)
+            .collect::<Vec<_>>();
+
+        // stop in the middle of the ring buffer, then continue from the state
+        let mut tscale = TScale::new_with_config(array, weight);
+        tscale.iter().take(7).for_each(drop);
+        for n in 0..10 {
+            tscale.nth_term(n).assert_approx(stepped[7 + n]);
+        }
+        tscale.iter().nth(2).unwrap().assert_approx(stepped[9]);
+        tscale.iter().next().unwrap().assert_approx(stepped[10]);
+    }
+
+    #[test]
+    fn test_step() {
+        let array = [1.0, 2.0, 3.0];
+        let weight = [0.4, 1.2, 0.3];
+        let stepped = TScale::new_with_config(array, weight)
+            .into_iter()
+            .take(20)
+            .collect::<Vec<_>>();
+
+        let mut tscale = TScale::new_with_config(array, weight);
+        assert_eq!(tscale.current_window(), array);
+        for expected in &stepped[3..10] {
+            let next = tscale.peek_next();
+            tscale.step().assert_approx(*expected);
+            next.assert_approx(*expected);
+        }
+        assert_eq!(tscale.current_window(), [stepped[7], stepped[8], stepped[9]]);
+
+        let snapshot = tscale.snapshot();
+        let next = tscale.step();
+        tscale.step();
+        tscale.restore(&snapshot);
+        assert_eq!(tscale.current_window(), *snapshot.window());
+        assert_eq!(tscale.step(), next);
+    }
+
+    #[test]
+    fn test_rewind() {
+        let array = [1.0, 2.0, 3.0];
+        let weight = [0.4, 1.2, 0.3];
+        let mut tscale = TScale::new_with_config(array, weight);
+        tscale.iter().take(4).for_each(drop);
+        let window = tscale.current_window();
+        // going backward amplifies the small characteristic roots, keep the distance short
+        for _ in 0..8 {
+            tscale.step();
+        }
+        tscale.rewind(8).unwrap();
+        tscale
+            .current_window()
+            .iter()
+            .zip(window)
+            .for_each(|(value, expected)| value.assert_approx(expected));
+
+        // back to the initial values
+        tscale.step_back().unwrap().assert_approx(3.9);
+        tscale.rewind(3).unwrap();
+        tscale
+            .current_window()
+            .iter()
+            .zip(array)
+            .for_each(|(value, expected)| value.assert_approx(expected));
+
+        let mut tscale = TScale::new_with_config(array, [1.0, 1.0, 0.0]);
+        tscale.step();
+        assert_eq!(tscale.rewind(1), Err(RewindError::ZeroLastWeight));
+        assert_eq!(tscale.current_window(), [2.0, 3.0, 5.0]);
+    }
+
+    #[test]
+    fn test_rate_at() {
+        let array = [0., 1.0];
+        let weight = [1., 1.];
+        compute_rate_at(49, array, weight)
+            .assert_approx(compute_rate_with_data(50, array, weight).last().unwrap());
+
+        // β sums to 1, so the ratio tends to 1 and the terms stay finite
+        compute_rate_at(1_000_000, [1.0, 2.0], [0.5, 0.5]).assert_approx(1.0);
+
+        // every t inside and past the initial values, and the single term window
+        let (array, weight) = ([1.0, 2.0, 4.0], [0.3, 0.2, 0.6]);
+        for (t, rate) in compute_rate_with_data(20, array, weight).enumerate() {
+            compute_rate_at(t, array, weight).assert_approx(rate);
+        }
+        compute_rate_at(1, [3.0], [1.5]).assert_approx(1.5);
+        compute_rate_at(300, [3.0], [1.5]).assert_approx(1.5);
+    }
 }
@@ -0,0 +1,372 @@
+//! Configurable solver for the limit rate.
+//!
+//! [`compute_limit_rate`](crate::tscale_rate::compute_limit_rate) solves
+//!
+//! f(r) = Σ βi*r^{1-i} - r = 0
+//!
+//! with fixed settings. [`RateSolver`] exposes them, and reports how the solve went.
+//!
+//! ```rust
+//! # use tscale_sequence::tscale_solver::{RateSolver, SolverMethod};
+//! let solution = RateSolver::new()
+//!     .method(SolverMethod::Brent)
+//!     .tolerance(1e-14)
+//!     .solve(&[0.5, 0.6, 0.7])
+//!     .unwrap();
+//! println!("{} after {} iterations", solution.rate, solution.iterations);
+//! ```
+
+use crate::tscale_rate::{validate_beta, RateError};
+
+/// Iterations before giving up with [`RateError::NotConverged`]
+pub const DEFAULT_MAX_ITERATIONS: usize = 1000;
+pub const DEFAULT_TOLERANCE: f64 = 1e-12;
+
+/// The root finding method
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SolverMethod {
+    /// Newton's method inside a bracket of the root, falling back to bisection
+    /// whenever a step would leave the bracket
+    ///
+    /// It converges for every valid weight vector and never evaluates f at r <= 0.
+    #[default]
+    Safeguarded,
+    /// Newton's method from the initial guess
+    Newton,
+    /// Halley's method from the initial guess, cubic convergence
+    Halley,
+    /// Bisection inside a bracket of the root, slow but always converges
+    Bisection,
+    /// Brent's method inside a bracket of the root
+    Brent,
+}
+
+/// Result of [`RateSolver::solve`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateSolution {
+    /// the limit rate r
+    pub rate: f64,
+    /// iterations used
+    pub iterations: usize,
+    /// |f(r)|
+    pub residual: f64,
+}
+
+/// Builder for the limit rate computation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateSolver {
+    tolerance: f64,
+    max_iterations: usize,
+    initial_guess: f64,
+    method: SolverMethod,
+}
+
+impl Default for RateSolver {
+    fn default() -> Self {
+        Self {
+            tolerance: DEFAULT_TOLERANCE,
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+            initial_guess: 1.0,
+            method: SolverMethod::default(),
+        }
+    }
+}
+
+impl RateSolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// stop once a step changes r by less than `tolerance`, relative to max(1, r)
+    pub const fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub const fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// starting point of Newton's and Halley's method
+    ///
+    /// The safeguarded method clamps it into the bracket, bisection and Brent's method
+    /// ignore it.
+    pub const fn initial_guess(mut self, initial_guess: f64) -> Self {
+        self.initial_guess = initial_guess;
+        self
+    }
+
+    pub const fn method(mut self, method: SolverMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// solve Σ βi*r^{1-i} = r
+    pub fn solve(&self, beta: &[f64]) -> Result<RateSolution, RateError> {
+        validate_beta(beta)?;
+        let (rate, iterations) = match self.method {
+            SolverMethod::Safeguarded => self.solve_safeguarded(beta)?,
+            SolverMethod::Newton => self.solve_open(beta, |f, df, _| f / df)?,
+            SolverMethod::Halley => self.solve_open(beta, |f, df, ddf| {
+                2.0 * f * df / (2.0 * df).mul_add(df, -f * ddf)
+            })?,
+            SolverMethod::Bisection => self.solve_bisection(beta)?,
+            SolverMethod::Brent => self.solve_brent(beta)?,
+        };
+        Ok(RateSolution {
+            rate,
+            iterations,
+            residual: evaluate(beta, rate).0.abs(),
+        })
+    }
+
+    /// Newton's method without [`validate_beta`], for betas of mixed sign
+    ///
+    /// Without the sign check there is no bracket of the root, so it may diverge or
+    /// converge to a root that is not the dominant one.
+    pub(crate) fn solve_unvalidated(&self, beta: &[f64]) -> Result<f64, RateError> {
+        self.solve_open(beta, |f, df, _| f / df).map(|(rate, _)| rate)
+    }
+
+    fn converged(&self, step: f64, x: f64) -> bool {
+        step.abs() <= self.tolerance * x.abs().max(1.0)
+    }
+
+    /// iterate x -= step(f, f', f'') from the initial guess
+    fn solve_open(
+        &self,
+        beta: &[f64],
+        step: impl Fn(f64, f64, f64) -> f64,
+    ) -> Result<(f64, usize), RateError> {
+        let mut x = self.initial_guess;
+        for iteration in 1..=self.max_iterations {
+            // r^{1-i} is only defined for r > 0
+            if x <= 0.0 {
+                return Err(RateError::NotConverged {
+                    iterations: iteration - 1,
+                });
+            }
+            let (f, df, ddf) = evaluate(beta, x);
+            let delta = step(f, df, ddf);
+            let x1 = x - delta;
+            if !x1.is_finite() {
+                return Err(RateError::NotConverged {
+                    iterations: iteration,
+                });
+            }
+            if self.converged(delta, x1) {
+                return Ok((x1, iteration));
+            }
+            x = x1;
+        }
+        Err(RateError::NotConverged {
+            iterations: self.max_iterations,
+        })
+    }
+
+    fn solve_safeguarded(&self, beta: &[f64]) -> Result<(f64, usize), RateError> {
+        let (mut low, mut high) = bracket(beta);
+        if low == high {
+            return Ok((low, 0));
+        }
+        let mut x = if self.initial_guess.is_nan() {
+            0.5 * (low + high)
+        } else {
+            self.initial_guess.clamp(low, high)
+        };
+        for iteration in 1..=self.max_iterations {
+            let (f, df, _) = evaluate(beta, x);
+            if f == 0.0 {
+                return Ok((x, iteration));
+            }
+            // f is decreasing, so a positive value means the root is above
+            if f > 0.0 {
+                low = x;
+            } else {
+                high = x;
+            }
+            let newton = x - f / df;
+            let x1 = if newton > low && newton < high {
+                newton
+            } else {
+                0.5 * (low + high)
+            };
+            if self.converged(x1 - x, x1) || self.converged(high - low, x1) {
+                return Ok((x1, iteration));
+            }
+            x = x1;
+        }
+        Err(RateError::NotConverged {
+            iterations: self.max_iterations,
+        })
+    }
+
+    fn solve_bisection(&self, beta: &[f64]) -> Result<(f64, usize), RateError> {
+        let (mut low, mut high) = bracket(beta);
+        for iteration in 1..=self.max_iterations {
+            let middle = 0.5 * (low + high);
+            let f = evaluate(beta, middle).0;
+            // f is decreasing, so a positive value means the root is above
+            if f > 0.0 {
+                low = middle;
+            } else {
+                high = middle;
+            }
+            if f == 0.0 || self.converged(high - low, middle) {
+                return Ok((middle, iteration));
+            }
+        }
+        Err(RateError::NotConverged {
+            iterations: self.max_iterations,
+        })
+    }
+
+    fn solve_brent(&self, beta: &[f64]) -> Result<(f64, usize), RateError> {
+        let f = |x: f64| evaluate(beta, x).0;
+        let (mut a, mut b) = bracket(beta);
+        let (mut fa, mut fb) = (f(a), f(b));
+        let (mut c, mut fc) = (b, fb);
+        let (mut d, mut e) = (b - a, b - a);
+        for iteration in 1..=self.max_iterations {
+            if fb.signum() == fc.signum() {
+                c = a;
+                fc = fa;
+                d = b - a;
+                e = d;
+            }
+            if fc.abs() < fb.abs() {
+                (a, b, c) = (b, c, b);
+                (fa, fb, fc) = (fb, fc, fb);
+            }
+            let tolerance = 0.5 * self.tolerance * b.abs().max(1.0);
+            let middle = 0.5 * (c - b);
+            if middle.abs() <= tolerance || fb == 0.0 {
+                return Ok((b, iteration));
+            }
+            if e.abs() >= tolerance && fa.abs() > fb.abs() {
+                // inverse quadratic interpolation, or the secant method if a == c
+                let s = fb / fa;
+                let (mut p, mut q) = if a == c {
+                    (2.0 * middle * s, 1.0 - s)
+                } else {
+                    let q = fa / fc;
+                    let r = fb / fc;
+                    (
+                        s * (2.0 * middle * q)
+                            .mul_add(q - r, -(b - a) * (r - 1.0)),
+                        (q - 1.0) * (r - 1.0) * (s - 1.0),
+                    )
+                };
+                if p > 0.0 {
+                    q = -q;
+                }
+                p = p.abs();
+                let limit = (3.0 * middle)
+                    .mul_add(q, -(tolerance * q).abs())
+                    .min((e * q).abs());
+                if 2.0 * p < limit {
+                    e = d;
+                    d = p / q;
+                } else {
+                    d = middle;
+                    e = d;
+                }
+            } else {
+                d = middle;
+                e = d;
+            }
+            a = b;
+            fa = fb;
+            b += if d.abs() > tolerance {
+                d
+            } else {
+                tolerance.copysign(middle)
+            };
+            fb = f(b);
+        }
+        Err(RateError::NotConverged {
+            iterations: self.max_iterations,
+        })
+    }
+}
+
+/// f(x) = Σ βi*x^{1-i} - x and its first two derivatives
+fn evaluate(beta: &[f64], x: f64) -> (f64, f64, f64) {
+    beta.iter().enumerate().skip(1).fold(
+        (beta[0] - x, -1.0, 0.0),
+        |(f, df, ddf), (i, beta)| {
+            let i = i as f64;
+            let power = x.powf(-i);
+            (
+                beta.mul_add(power, f),
+                (-beta * i).mul_add(power / x, df),
+                (beta * i * (i + 1.0)).mul_add(power / (x * x), ddf),
+            )
+        },
+    )
+}
+
+/// An interval that contains the root, for valid betas
+///
+/// If r > 1 then r = Σ βi*r^{1-i} <= Σ βi, and if r < 1 then r >= Σ βi,
+/// so the root lies between 1 and Σ βi.
+fn bracket(beta: &[f64]) -> (f64, f64) {
+    let sum = beta.iter().sum::<f64>();
+    (sum.min(1.0), sum.max(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use approximately::ApproxEq;
+
+    use super::*;
+
+    #[test]
+    fn test_methods() {
+        let weight = [0.4, 1.2, 0.3, 0.1, 0.0];
+        let methods = [
+            SolverMethod::Safeguarded,
+            SolverMethod::Newton,
+            SolverMethod::Halley,
+            SolverMethod::Bisection,
+            SolverMethod::Brent,
+        ];
+        for method in methods {
+            let solution = RateSolver::new().method(method).solve(&weight).unwrap();
+            solution.rate.assert_approx(1.4246802050888951);
+            assert!(solution.residual < 1e-10, "{method:?}: {solution:?}");
+        }
+
+        // β sums to 1, so the bracket collapses to the root
+        let solution = RateSolver::new()
+            .method(SolverMethod::Brent)
+            .solve(&[0.5, 0.5])
+            .unwrap();
+        solution.rate.assert_approx(1.0);
+
+        assert_eq!(
+            RateSolver::new()
+                .method(SolverMethod::Newton)
+                .max_iterations(2)
+                .solve(&weight),
+            Err(RateError::NotConverged { iterations: 2 })
+        );
+    }
+
+    #[test]
+    fn test_safeguarded() {
+        let weight = [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.05];
+        for guess in [-3.0, 0.0, 1e-3, 1.0, 1e6] {
+            let solution = RateSolver::new().initial_guess(guess).solve(&weight).unwrap();
+            solution.rate.assert_approx(0.05_f64.powf(0.1));
+        }
+        assert_eq!(
+            RateSolver::new()
+                .method(SolverMethod::Newton)
+                .initial_guess(0.0)
+                .solve(&weight),
+            Err(RateError::NotConverged { iterations: 0 })
+        );
+    }
+}
@@ -0,0 +1,394 @@
+//! Stochastic t-scale sequences.
+//!
+//! Every tick either the weights are drawn around their means, or the new value is
+//! multiplied by a random factor, see [`Noise`]. The random numbers come from a small
+//! built-in generator, so a seed always gives the same sample path.
+//!
+//! ```rust
+//! # use tscale_sequence::{tscale_sequence::TScale, tscale_stochastic::Noise};
+//! let mut tscale = TScale::new_with_config([100.0, 100.0], [0.5, 0.6])
+//!     .with_noise(Noise::Weights { std_dev: 0.1 }, 42);
+//! let path = tscale.sample_path(100);
+//! ```
+//!
+//! [`MonteCarlo`] runs many sample paths and summarizes them.
+
+use std::f64::consts::TAU;
+
+use crate::{
+    tscale_jump,
+    tscale_rate::{try_compute_limit_rate, RateError},
+    tscale_sequence::TScale,
+};
+
+/// The SplitMix64 pseudo random number generator
+///
+/// Fast and good enough for simulations, but not cryptographically secure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitMix64 {
+    state: u64,
+    /// Box–Muller produces normal values in pairs
+    spare: Option<f64>,
+}
+
+impl SplitMix64 {
+    pub const fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+            spare: None,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+
+    /// standard normal, with the Box–Muller transform
+    pub fn next_normal(&mut self) -> f64 {
+        if let Some(spare) = self.spare.take() {
+            return spare;
+        }
+        // 1 - u lies in (0, 1], so the logarithm is finite
+        let radius = (-2.0 * (1.0 - self.next_f64()).ln()).sqrt();
+        let angle = TAU * self.next_f64();
+        self.spare = Some(radius * angle.sin());
+        radius * angle.cos()
+    }
+}
+
+/// How randomness enters a [`StochasticTScale`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Noise {
+    /// every tick each weight is drawn from N(βi, (σ*βi)²), negative draws become 0
+    Weights { std_dev: f64 },
+    /// every new value is multiplied by e^(σ*z - σ²/2) with z ~ N(0, 1),
+    /// a log-normal factor with mean 1
+    Multiplicative { std_dev: f64 },
+}
+
+impl Noise {
+    /// draw the weights of one tick into `drawn`
+    pub(crate) fn draw_weights(
+        &self,
+        weight: &[f64],
+        drawn: &mut [f64],
+        rng: &mut SplitMix64,
+    ) {
+        match *self {
+            Self::Weights { std_dev } => {
+                drawn.iter_mut().zip(weight).for_each(|(drawn, beta)| {
+                    *drawn = (beta * std_dev.mul_add(rng.next_normal(), 1.0)).max(0.0);
+                });
+            }
+            Self::Multiplicative { .. } => drawn.copy_from_slice(weight),
+        }
+    }
+
+    /// the factor of the new value
+    pub(crate) fn draw_factor(&self, rng: &mut SplitMix64) -> f64 {
+        match *self {
+            Self::Weights { .. } => 1.0,
+            Self::Multiplicative { std_dev } => {
+                std_dev.mul_add(rng.next_normal(), -0.5 * std_dev * std_dev).exp()
+            }
+        }
+    }
+}
+
+impl<const C: usize> TScale<f64, C> {
+    /// make every step random, reproducibly for the same `seed`
+    pub const fn with_noise(self, noise: Noise, seed: u64) -> StochasticTScale<C> {
+        StochasticTScale {
+            tscale: self,
+            noise,
+            rng: SplitMix64::new(seed),
+        }
+    }
+}
+
+/// A [`TScale`] with random steps, created by [`TScale::with_noise`]
+///
+/// The weights of the [`TScale`] are the means of the drawn weights.
+pub struct StochasticTScale<const C: usize> {
+    tscale: TScale<f64, C>,
+    noise: Noise,
+    rng: SplitMix64,
+}
+
+impl<const C: usize> StochasticTScale<C> {
+    /// advance one tick and return the new value
+    pub fn step(&mut self) -> f64 {
+        let TScale {
+            array,
+            weight,
+            head,
+        } = &mut self.tscale;
+        let mut drawn = [0.0; C];
+        self.noise.draw_weights(weight, &mut drawn, &mut self.rng);
+        tscale_jump::step_ring(array, &drawn, head);
+        let newest = (*head + C - 1) % C;
+        array[newest] *= self.noise.draw_factor(&mut self.rng);
+        array[newest]
+    }
+
+    /// the next `len` new values
+    pub fn sample_path(&mut self, len: usize) -> Vec<f64> {
+        (0..len).map(|_| self.step()).collect()
+    }
+
+    /// the last C values, oldest first
+    pub fn current_window(&self) -> [f64; C] {
+        self.tscale.current_window()
+    }
+
+    /// the sequence without the noise
+    pub const fn into_inner(self) -> TScale<f64, C> {
+        self.tscale
+    }
+}
+
+/// Monte Carlo simulation of a [`StochasticTScale`]
+///
+/// ```rust
+/// # use tscale_sequence::tscale_stochastic::{MonteCarlo, Noise};
+/// let result = MonteCarlo::new([100.0, 100.0], [0.5, 0.6], Noise::Weights { std_dev: 0.1 })
+///     .runs(500)
+///     .ticks(50)
+///     .run()
+///     .unwrap();
+/// println!("{} vs {}", result.growth_rate, result.limit_rate);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonteCarlo<const C: usize> {
+    array: [f64; C],
+    weight: [f64; C],
+    noise: Noise,
+    runs: usize,
+    ticks: usize,
+    seed: u64,
+    percentiles: Vec<f64>,
+}
+
+/// Result of [`MonteCarlo::run`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonteCarloResult {
+    /// the requested percentiles, between 0 and 100
+    pub percentiles: Vec<f64>,
+    /// `bands[k][t]` is the `percentiles[k]` percentile of the new value at tick t
+    pub bands: Vec<Vec<f64>>,
+    /// the geometric mean growth per tick of the sum of the last C values over the second
+    /// half of the ticks, averaged over the runs that did not die out, or 0 if all did
+    pub growth_rate: f64,
+    /// the runs that died out, i.e. their last C values are all 0
+    pub extinct: usize,
+    /// [`compute_limit_rate`](crate::tscale_rate::compute_limit_rate) of the mean weights
+    pub limit_rate: f64,
+}
+
+impl<const C: usize> MonteCarlo<C> {
+    /// same arguments as [`TScale::new_with_config`], plus the noise
+    ///
+    /// By default it runs 1000 paths of 100 ticks with seed 0, and reports the 5th,
+    /// 50th and 95th percentile.
+    pub fn new(array: [f64; C], weight: [f64; C], noise: Noise) -> Self {
+        Self {
+            array,
+            weight,
+            noise,
+            runs: 1000,
+            ticks: 100,
+            seed: 0,
+            percentiles: vec![5.0, 50.0, 95.0],
+        }
+    }
+
+    pub const fn runs(mut self, runs: usize) -> Self {
+        self.runs = runs;
+        self
+    }
+
+    pub const fn ticks(mut self, ticks: usize) -> Self {
+        self.ticks = ticks;
+        self
+    }
+
+    pub const fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// percentiles between 0 and 100
+    pub fn percentiles(mut self, percentiles: impl Into<Vec<f64>>) -> Self {
+        self.percentiles = percentiles.into();
+        self
+    }
+
+    /// simulate every run
+    ///
+    /// Fails if the mean weights have no limit rate, see
+    /// [`try_compute_limit_rate`](crate::tscale_rate::try_compute_limit_rate), or if
+    /// there are no runs or no ticks.
+    pub fn run(&self) -> Result<MonteCarloResult, RateError> {
+        let limit_rate = try_compute_limit_rate(&self.weight)?;
+        if self.runs == 0 {
+            return Err(RateError::TooFewReplicates {
+                needed: 1,
+                found: 0,
+            });
+        }
+        if self.ticks == 0 {
+            return Err(RateError::TooFewTicks {
+                needed: 1,
+                found: 0,
+            });
+        }
+        let mut seeds = SplitMix64::new(self.seed);
+        let paths = (0..self.runs)
+            .map(|_| {
+                TScale::new_with_config(self.array, self.weight)
+                    .with_noise(self.noise, seeds.next_u64())
+                    .sample_path(self.ticks)
+            })
+            .collect::<Vec<_>>();
+
+        // the sum of the last C values after `ticks` ticks
+        let window_sum = |path: &[f64], ticks: usize| -> f64 {
+            self.array.iter().chain(&path[..ticks]).rev().take(C).sum()
+        };
+        let half = self.ticks / 2;
+        let log_growths = paths
+            .iter()
+            .filter(|path| window_sum(path, self.ticks) > 0.0)
+            .map(|path| {
+                (window_sum(path, self.ticks) / window_sum(path, half)).ln()
+                    / (self.ticks - half) as f64
+            })
+            .collect::<Vec<_>>();
+        let growth_rate = if log_growths.is_empty() {
+            0.0
+        } else {
+            (log_growths.iter().sum::<f64>() / log_growths.len() as f64).exp()
+        };
+
+        let mut bands = vec![Vec::with_capacity(self.ticks); self.percentiles.len()];
+        let mut values = Vec::with_capacity(self.runs);
+        for t in 0..self.ticks {
+            values.clear();
+            values.extend(paths.iter().map(|path| path[t]));
+            values.sort_by(f64::total_cmp);
+            for (band, percentile) in bands.iter_mut().zip(&self.percentiles) {
+                band.push(percentile_of_sorted(&values, *percentile));
+            }
+        }
+        Ok(MonteCarloResult {
+            percentiles: self.percentiles.clone(),
+            bands,
+            growth_rate,
+            extinct: self.runs - log_growths.len(),
+            limit_rate,
+        })
+    }
+}
+
+/// The percentile of sorted values, interpolating linearly between the closest ranks
+fn percentile_of_sorted(values: &[f64], percentile: f64) -> f64 {
+    if values.is_empty() {
+        return f64::NAN;
+    }
+    let rank = (percentile / 100.0).clamp(0.0, 1.0) * (values.len() - 1) as f64;
+    let (low, high) = (rank.floor() as usize, rank.ceil() as usize);
+    (values[high] - values[low]).mul_add(rank - low as f64, values[low])
+}
+
+#[cfg(test)]
+mod tests {
+    use approximately::ApproxEq;
+
+    use super::*;
+
+    #[test]
+    fn test_sample_path() {
+        let array = [100.0, 100.0];
+        let weight = [0.5, 0.6];
+        let noise = Noise::Weights { std_dev: 0.2 };
+        let path = |seed| {
+            TScale::new_with_config(array, weight)
+                .with_noise(noise, seed)
+                .sample_path(50)
+        };
+        assert_eq!(path(7), path(7));
+        assert_ne!(path(7), path(8));
+        assert!(path(7).iter().all(|value| *value >= 0.0));
+
+        // without noise it is the deterministic sequence
+        for noise in [Noise::Weights { std_dev: 0.0 }, Noise::Multiplicative { std_dev: 0.0 }] {
+            let mut expected = TScale::new_with_config(array, weight);
+            let mut tscale = TScale::new_with_config(array, weight).with_noise(noise, 1);
+            tscale.step().assert_approx(expected.step());
+        }
+
+        let mut rng = SplitMix64::new(3);
+        let normals = (0..10_000).map(|_| rng.next_normal()).collect::<Vec<_>>();
+        let mean = normals.iter().sum::<f64>() / 10_000.0;
+        let variance = normals.iter().map(|z| (z - mean).powi(2)).sum::<f64>() / 10_000.0;
+        assert!(mean.abs() < 0.05 && (variance - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_monte_carlo() {
+        let result = MonteCarlo::new([100.0, 100.0], [0.5, 0.6], Noise::Multiplicative {
+            std_dev: 0.05,
+        })
+        .runs(400)
+        .ticks(60)
+        .seed(11)
+        .run()
+        .unwrap();
+        assert_eq!(result.bands.len(), 3);
+        assert!(result.bands.iter().all(|band| band.len() == 60));
+        for t in 0..60 {
+            assert!(result.bands[0][t] <= result.bands[1][t]);
+            assert!(result.bands[1][t] <= result.bands[2][t]);
+        }
+        // a mean 1 factor only lowers the log growth by σ²/2
+        let expected = result.limit_rate.ln() - 0.05 * 0.05 / 2.0;
+        assert!((result.growth_rate.ln() - expected).abs() < 0.01);
+
+        assert_eq!(percentile_of_sorted(&[1.0, 2.0, 3.0, 4.0, 5.0], 50.0), 3.0);
+        assert_eq!(percentile_of_sorted(&[1.0, 2.0], 25.0), 1.25);
+
+        // runs die out once a weight is drawn as 0, they are left out of the growth rate
+        let result = MonteCarlo::new([1.0], [1.2], Noise::Weights { std_dev: 0.5 })
+            .runs(50)
+            .ticks(20)
+            .run()
+            .unwrap();
+        assert!(result.extinct > 0 && result.extinct < 50, "{}", result.extinct);
+        assert!(result.growth_rate > 1.0 && result.growth_rate.is_finite());
+        let result = MonteCarlo::new([1.0], [1.0], Noise::Weights { std_dev: 2.0 })
+            .runs(10)
+            .ticks(50)
+            .run()
+            .unwrap();
+        assert_eq!((result.extinct, result.growth_rate), (10, 0.0));
+
+        let monte_carlo = MonteCarlo::new([1.0], [1.0], Noise::Weights { std_dev: 0.1 });
+        assert_eq!(
+            monte_carlo.clone().runs(0).run(),
+            Err(RateError::TooFewReplicates { needed: 1, found: 0 })
+        );
+        assert_eq!(
+            monte_carlo.ticks(0).run(),
+            Err(RateError::TooFewTicks { needed: 1, found: 0 })
+        );
+    }
+}